@@ -0,0 +1,235 @@
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::BytesMut;
+use futures::{future, Async, Future, Stream};
+use tokio::codec::{BytesCodec, Encoder, FramedRead};
+use tokio::io::{read_to_end, write_all};
+use tokio::net::TcpStream;
+use tokio::timer::Timeout;
+use url::Url;
+
+use crate::errors::Error;
+use crate::request::{Request, RequestCodec};
+use crate::response::Response;
+use crate::types::ItemType;
+
+/// Makes requests to gopher servers over TCP.
+pub struct Client;
+
+/// How a request went, as measured by `Client::fetch_metrics`. Times are counted from the start
+/// of the request, and are `None` for the steps it didn't get to.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    pub url: Url,
+    pub item_type: ItemType,
+    pub started: SystemTime,
+    /// The address the host name resolved to.
+    pub address: Option<SocketAddr>,
+    /// The bytes sent to the server: the selector and a line ending.
+    pub request: Vec<u8>,
+    pub received: usize,
+    pub connected: Option<Duration>,
+    pub first_byte: Option<Duration>,
+    pub completed: Option<Duration>,
+}
+
+impl Metrics {
+    /// Metrics for a request that hasn't been sent yet.
+    pub fn new(request: &Request) -> Self {
+        Metrics {
+            url: request.url.clone(),
+            item_type: request.item_type,
+            started: SystemTime::now(),
+            address: None,
+            request: request.to_bytes(),
+            received: 0,
+            connected: None,
+            first_byte: None,
+            completed: None,
+        }
+    }
+}
+
+/// Metrics being filled in as a request goes on, with the time it was started.
+type Recorder = Arc<Mutex<(Instant, Metrics)>>;
+
+impl Client {
+    /// Sends the request and parses the response as UTF-8.
+    pub fn request_async(request: Request) -> impl Future<Item = Response, Error = Error> {
+        let item_type = request.item_type;
+        let url = request.url.clone();
+        Client::fetch_raw(request).and_then(move |buf| Response::from_buf(url, item_type, buf))
+    }
+
+    /// Like `request_async`, but gives up after `timeout` and measures how the exchange went, as
+    /// `fetch_metrics` does.
+    pub fn request_metrics(
+        request: Request,
+        timeout: Duration,
+    ) -> impl Future<Item = (Response, Metrics), Error = (Error, Box<Metrics>)> {
+        let item_type = request.item_type;
+        let url = request.url.clone();
+        Client::fetch_metrics(request, timeout, |_| ()).and_then(move |(buf, metrics)| {
+            match Response::from_buf(url, item_type, buf) {
+                Ok(response) => Ok((response, metrics)),
+                Err(err) => Err((err, Box::new(metrics))),
+            }
+        })
+    }
+
+    /// Sends the request and reads the raw response body until the server closes the connection.
+    pub fn fetch_raw(request: Request) -> impl Future<Item = Vec<u8>, Error = Error> {
+        // read the entire body without buffering
+        let recv_response = |stream: TcpStream| {
+            read_to_end(stream, Vec::new())
+                .map(|(_, buf)| buf)
+                .map_err(Error::from)
+        };
+
+        let recorder = recorder(&request);
+        Client::send(request, recorder).and_then(recv_response)
+    }
+
+    /// Like `fetch_raw`, but gives up if the whole exchange takes longer than `timeout`.
+    pub fn fetch_timeout(
+        request: Request,
+        timeout: Duration,
+    ) -> impl Future<Item = Vec<u8>, Error = Error> {
+        let url = request.url.clone();
+        time_out(Client::fetch_raw(request), url, timeout)
+    }
+
+    /// Like `fetch_timeout`, but calls `progress` with the number of bytes received so far
+    /// whenever more of the response arrives.
+    pub fn fetch_progress<F>(
+        request: Request,
+        timeout: Duration,
+        progress: F,
+    ) -> impl Future<Item = Vec<u8>, Error = Error>
+    where
+        F: FnMut(usize) + Send + 'static,
+    {
+        Client::fetch_metrics(request, timeout, progress)
+            .map(|(buf, _)| buf)
+            .map_err(|(err, _)| err)
+    }
+
+    /// Like `fetch_progress`, but also measures how the exchange went. The metrics are given
+    /// whether the request succeeds or not, up to the point where it failed; they're boxed on
+    /// failure to keep the error small.
+    pub fn fetch_metrics<F>(
+        request: Request,
+        timeout: Duration,
+        mut progress: F,
+    ) -> impl Future<Item = (Vec<u8>, Metrics), Error = (Error, Box<Metrics>)>
+    where
+        F: FnMut(usize) + Send + 'static,
+    {
+        let url = request.url.clone();
+        let recorder = recorder(&request);
+        let receiving = recorder.clone();
+        let fetch = Client::send(request, recorder.clone()).and_then(move |stream| {
+            let completing = receiving.clone();
+            FramedRead::new(stream, BytesCodec::new())
+                .map_err(Error::from)
+                .fold(Vec::new(), move |mut buf, chunk| {
+                    buf.extend_from_slice(&chunk);
+                    record(&receiving, |metrics, elapsed| {
+                        metrics.first_byte = metrics.first_byte.or(Some(elapsed));
+                        metrics.received = buf.len();
+                    });
+                    progress(buf.len());
+                    Ok::<_, Error>(buf)
+                })
+                .map(move |buf| {
+                    record(&completing, |metrics, elapsed| {
+                        metrics.completed = Some(elapsed)
+                    });
+                    buf
+                })
+        });
+        time_out(fetch, url, timeout).then(move |result| {
+            let metrics = recorder.lock().unwrap().1.clone();
+            match result {
+                Ok(buf) => Ok((buf, metrics)),
+                Err(err) => Err((err, Box::new(metrics))),
+            }
+        })
+    }
+
+    /// Looks up the server, connects to it and sends the request, giving the connection to read
+    /// the response from.
+    fn send(request: Request, recorder: Recorder) -> impl Future<Item = TcpStream, Error = Error> {
+        let host = request.host.clone();
+        let port = request.port;
+        let mut buf = BytesMut::new();
+        let encoded = RequestCodec::new().encode(request, &mut buf);
+
+        let resolved = recorder.clone();
+        future::result(encoded)
+            .and_then(move |_| resolve(host, port))
+            .and_then(move |addr| {
+                record(&resolved, |metrics, _| metrics.address = Some(addr));
+                TcpStream::connect(&addr).map_err(Error::from)
+            })
+            .and_then(move |stream| {
+                record(&recorder, |metrics, elapsed| {
+                    metrics.connected = Some(elapsed)
+                });
+                write_all(stream, buf)
+                    .map(|(stream, _)| stream)
+                    .map_err(Error::from)
+            })
+    }
+}
+
+/// Looks up the address of a host on a thread the pool sets aside for blocking calls, so that a
+/// slow resolver doesn't hold up other requests. Outside of a thread pool, the lookup is made in
+/// place.
+fn resolve(host: String, port: u16) -> impl Future<Item = SocketAddr, Error = Error> {
+    let lookup = move || {
+        (host.as_str(), port)
+            .to_socket_addrs()
+            .and_then(|mut iter| {
+                iter.next()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host not found"))
+            })
+    };
+    future::poll_fn(move || match tokio_threadpool::blocking(&lookup) {
+        Ok(Async::Ready(addr)) => addr.map(Async::Ready),
+        Ok(Async::NotReady) => Ok(Async::NotReady),
+        Err(_) => lookup().map(Async::Ready),
+    })
+    .map_err(Error::from)
+}
+
+/// Fails with `Error::Timeout` if `fetch` takes longer than `timeout`.
+fn time_out<F>(fetch: F, url: Url, timeout: Duration) -> impl Future<Item = Vec<u8>, Error = Error>
+where
+    F: Future<Item = Vec<u8>, Error = Error>,
+{
+    Timeout::new(fetch, timeout).map_err(move |err| {
+        if err.is_elapsed() {
+            Error::Timeout(url)
+        } else if err.is_inner() {
+            err.into_inner().unwrap()
+        } else {
+            let timer = err.into_timer().unwrap();
+            Error::from(io::Error::other(timer))
+        }
+    })
+}
+
+fn recorder(request: &Request) -> Recorder {
+    Arc::new(Mutex::new((Instant::now(), Metrics::new(request))))
+}
+
+/// Records something that happened just now.
+fn record(recorder: &Recorder, update: impl FnOnce(&mut Metrics, Duration)) {
+    let mut recorder = recorder.lock().unwrap();
+    let elapsed = recorder.0.elapsed();
+    update(&mut recorder.1, elapsed);
+}
@@ -0,0 +1,41 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+use url::Url;
+
+use crate::types::ItemType;
+
+#[derive(Debug)]
+pub enum Error {
+    IO(io::Error),
+    /// The URL doesn't name a gopher server.
+    InvalidUrl(String),
+    /// The server didn't finish its response in time.
+    Timeout(Url),
+    /// The response is of a type that can't be parsed.
+    UnsupportedType(ItemType),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::IO(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::IO(err) => write!(f, "IO error: {}", err),
+            Error::InvalidUrl(url) => write!(f, "Invalid URL: {}", url),
+            Error::Timeout(url) => write!(f, "{} took too long to respond", url),
+            Error::UnsupportedType(item_type) => write!(
+                f,
+                "Items of type '{}' aren't supported",
+                item_type.encode() as char
+            ),
+        }
+    }
+}
+
+impl StdError for Error {}
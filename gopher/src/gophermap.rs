@@ -0,0 +1,215 @@
+use std::fmt;
+
+use url::percent_encoding::{utf8_percent_encode, DEFAULT_ENCODE_SET};
+use url::Url;
+
+use crate::types::ItemType;
+
+/// A single line of a gopher menu or gophermap file.
+///
+/// Server responses always have all four fields. Gophermap files on disk may leave out the host
+/// and port (the item is on the same server), and lines without any tab are plain text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub item_type: ItemType,
+    pub display: String,
+    pub selector: String,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl Line {
+    /// A line of plain text.
+    pub fn info(text: impl Into<String>) -> Self {
+        Line {
+            item_type: ItemType::Other(b'i'),
+            display: text.into(),
+            selector: String::new(),
+            host: None,
+            port: None,
+        }
+    }
+
+    /// Parses a line, treating lines without any tab as plain text.
+    ///
+    /// ```
+    /// use gopher::gophermap::Line;
+    /// use gopher::ItemType;
+    ///
+    /// let line = Line::parse("1Phlog\t/phlog\texample.com\t70");
+    /// assert_eq!(line.item_type, ItemType::Dir);
+    /// assert_eq!(line.display, "Phlog");
+    /// assert_eq!(line.url().unwrap().as_str(), "gopher://example.com/1/phlog");
+    /// ```
+    pub fn parse(line: &str) -> Self {
+        if !line.contains('\t') {
+            return Line::info(line);
+        }
+
+        let item_type = ItemType::decode(line.as_bytes()[0]);
+        let first_len = line.chars().next().map(char::len_utf8).unwrap_or(0);
+        let mut parts = line[first_len..].split('\t');
+        let display = parts.next().unwrap_or("").to_owned();
+        let selector = parts.next().unwrap_or("").to_owned();
+        let host = parts
+            .next()
+            .filter(|host| !host.is_empty())
+            .map(|host| host.to_owned());
+        let port = parts.next().and_then(|port| port.trim().parse().ok());
+        Line {
+            item_type,
+            display,
+            selector,
+            host,
+            port,
+        }
+    }
+
+    /// Whether this is a line of text rather than a link.
+    pub fn is_info(&self) -> bool {
+        self.item_type == ItemType::Other(b'i')
+    }
+
+    /// The gopher URL this line links to, if it names a host.
+    pub fn url(&self) -> Option<Url> {
+        let host = self.host.as_ref()?;
+        selector_url(
+            host,
+            self.port.unwrap_or(70),
+            self.item_type,
+            &self.selector,
+        )
+    }
+}
+
+/// Builds the URL for a selector on the given server.
+///
+/// Characters that mean something else in a URL, like "?" and "#", are escaped.
+pub fn selector_url(host: &str, port: u16, item_type: ItemType, selector: &str) -> Option<Url> {
+    let url = format!(
+        "gopher://{}:{}/{}/{}",
+        host,
+        port,
+        item_type.encode() as char,
+        utf8_percent_encode(selector.trim_start_matches('/'), DEFAULT_ENCODE_SET)
+    );
+    match Url::parse(&url) {
+        Ok(url) => Some(url),
+        Err(err) => {
+            error!("Error parsing URL {}: {}", url, err);
+            None
+        }
+    }
+}
+
+/// Parses a whole menu, skipping empty lines and the terminating ".".
+pub fn parse(text: &str) -> Vec<Line> {
+    text.lines()
+        .filter(|line| !line.is_empty() && *line != ".")
+        .map(Line::parse)
+        .collect()
+}
+
+/// Widest display string most clients show without cutting it off.
+pub const MAX_WIDTH: usize = 70;
+
+/// Item types that clients know what to do with: the ones in RFC 1436 and common extensions.
+const KNOWN_TYPES: &str = "0123456789+TgIihsdp:;<cM";
+
+/// A line of a gophermap that probably doesn't show the way it was meant to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Warning {
+    /// The line number, starting at 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Checks a gophermap file for links without tabs, bad ports, lines that are too long and item
+/// types clients won't know.
+///
+/// ```
+/// let warnings = gopher::gophermap::lint("1Phlog /phlog\n0About\t/about.txt\texample.com\tgopher");
+/// assert_eq!(warnings.len(), 2);
+/// assert_eq!(warnings[0].line, 1);
+/// assert_eq!(warnings[1].to_string(), "line 2: the port \"gopher\" isn't a number");
+/// ```
+pub fn lint(text: &str) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let mut warn = |message: String| {
+            warnings.push(Warning {
+                line: n + 1,
+                message,
+            })
+        };
+        if line.is_empty() || line == "." {
+            continue;
+        }
+
+        let first = line.chars().next().unwrap_or(' ');
+        if !line.contains('\t') {
+            if (first.is_ascii_digit() || first == '+') && line.len() > 1 {
+                warn(format!(
+                    "starts with the item type '{}' but has no tab, so it's shown as text",
+                    first
+                ));
+            }
+            if line.chars().count() > MAX_WIDTH {
+                warn(format!(
+                    "is {} characters long, more than the {} most clients show",
+                    line.chars().count(),
+                    MAX_WIDTH
+                ));
+            }
+            continue;
+        }
+
+        let fields = line[first.len_utf8()..].split('\t').collect::<Vec<_>>();
+        if !KNOWN_TYPES.contains(first) {
+            warn(format!("the item type '{}' isn't one clients know", first));
+        }
+        let display = fields[0].chars().count();
+        if display > MAX_WIDTH {
+            warn(format!(
+                "the display string is {} characters long, more than the {} most clients show",
+                display, MAX_WIDTH
+            ));
+        }
+        let host = fields.get(2).map(|host| host.trim()).unwrap_or("");
+        match fields.get(3).map(|port| port.trim()) {
+            Some(port) if first != 'i' => match port.parse::<u16>() {
+                Ok(0) => warn("the port is 0".to_owned()),
+                Ok(_) => (),
+                Err(_) => warn(format!("the port {:?} isn't a number", port)),
+            },
+            None if !host.is_empty() => warn(format!(
+                "has the host {:?} but no port after another tab",
+                host
+            )),
+            _ => (),
+        }
+    }
+    warnings
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}\t{}",
+            self.item_type.encode() as char,
+            self.display,
+            self.selector
+        )?;
+        if let Some(host) = &self.host {
+            write!(f, "\t{}\t{}", host, self.port.unwrap_or(70))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+//! The Gopher protocol (RFC 1436): requests, responses, menus and an async client.
+//!
+//! ```no_run
+//! use futures::Future;
+//! use gopher::{Client, Request, ResponseKind};
+//! use url::Url;
+//!
+//! let url = Url::parse("gopher://gopher.floodgap.com/1/").unwrap();
+//! let request = Request::from_url(url).unwrap();
+//! let fetch = Client::request_async(request)
+//!     .map(|response| {
+//!         if let ResponseKind::Menu(entries) = response.kind {
+//!             println!("{} menu entries", entries.len());
+//!         }
+//!     })
+//!     .map_err(|err| eprintln!("{}", err));
+//! tokio::run(fetch);
+//! ```
+
+#[macro_use]
+extern crate log;
+
+mod client;
+mod errors;
+pub mod gophermap;
+mod request;
+mod response;
+mod types;
+
+pub use self::client::{Client, Metrics};
+pub use self::errors::Error;
+pub use self::request::{parent_url, search_url, Request, RequestCodec};
+pub use self::response::{
+    decode_text, is_bad_line, MenuEntry, MenuLine, Response, ResponseCodec, ResponseKind,
+};
+pub use self::types::ItemType;
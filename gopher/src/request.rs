@@ -0,0 +1,231 @@
+use std::io;
+
+use bytes::BytesMut;
+use tokio::codec::{Decoder, Encoder};
+use url::percent_encoding::{percent_decode, utf8_percent_encode, DEFAULT_ENCODE_SET};
+use url::Url;
+
+use crate::errors::Error;
+use crate::gophermap::selector_url;
+use crate::types::ItemType;
+
+/// Longest request line a server accepts.
+const MAX_LINE: usize = 4096;
+
+/// A request for a single item on a gopher server.
+#[derive(Debug)]
+pub struct Request {
+    pub url: Url,
+    pub host: String,
+    pub port: u16,
+    pub item_type: ItemType,
+    /// The selector sent to the server, without the leading slash.
+    pub resource: String,
+}
+
+impl Request {
+    /// Builds a request for a `gopher://` URL, with the item type as the first path segment.
+    ///
+    /// ```
+    /// use gopher::{ItemType, Request};
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("gopher://example.com/0/docs/readme.txt").unwrap();
+    /// let request = Request::from_url(url).unwrap();
+    /// assert_eq!(request.port, 70);
+    /// assert_eq!(request.item_type, ItemType::File);
+    /// assert_eq!(request.resource, "docs/readme.txt");
+    /// ```
+    pub fn from_url(url: Url) -> Result<Self, Error> {
+        Request::with_default_port(url, 70)
+    }
+
+    /// Builds a request, using `default_port` if the URL doesn't have a port.
+    pub fn with_default_port(url: Url, default_port: u16) -> Result<Self, Error> {
+        // the address is resolved by the client, so that requests can be built without network
+        let host = match url.host_str() {
+            Some(host) => host.to_owned(),
+            None => return Err(Error::InvalidUrl(url.to_string())),
+        };
+        let port = url.port().unwrap_or(default_port);
+
+        let (item_type, resource) = url
+            .path_segments()
+            .and_then(|mut iter| iter.next().map(|item| (item, iter)))
+            .and_then(|(first_arg, iter)| {
+                if first_arg.is_empty() {
+                    return None;
+                }
+                let ty = ItemType::decode(first_arg.as_bytes()[0]);
+                let rest = iter.collect::<Vec<_>>().join("/");
+                Some((ty, rest))
+            })
+            .unwrap_or_else(|| (ItemType::Dir, String::new()));
+
+        Ok(Request {
+            url,
+            host,
+            port,
+            item_type,
+            resource,
+        })
+    }
+
+    /// The selector as the server sees it, without the leading slash or the query of a search.
+    pub fn selector(&self) -> String {
+        let resource = percent_decode(self.resource.as_bytes()).decode_utf8_lossy();
+        resource.split('\t').next().unwrap_or("").to_owned()
+    }
+
+    /// The query of a search, if there is one.
+    pub fn query(&self) -> Option<String> {
+        let resource = percent_decode(self.resource.as_bytes()).decode_utf8_lossy();
+        resource.split_once('\t').map(|(_, query)| query.to_owned())
+    }
+
+    /// The line sent to the server: the selector, with a tab before the query of a search, and a
+    /// line ending.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = percent_decode(self.resource.as_bytes()).collect::<Vec<_>>();
+        bytes.push(b'\n');
+        bytes
+    }
+
+    /// A name to save the resource under: the last part of the selector, if it has one.
+    pub fn file_name(&self) -> Option<String> {
+        let resource = percent_decode(self.resource.as_bytes()).decode_utf8_lossy();
+        resource
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty() && *name != "." && *name != "..")
+            .map(str::to_owned)
+    }
+}
+
+/// The URL for searching the index server at `url` for `query` (a "%09" and the query are added
+/// to the path, as in RFC 4266).
+///
+/// ```
+/// use url::Url;
+///
+/// let url = Url::parse("gopher://example.com/7/search").unwrap();
+/// let url = gopher::search_url(&url, "cute cats");
+/// assert_eq!(url.as_str(), "gopher://example.com/7/search%09cute%20cats");
+/// ```
+pub fn search_url(url: &Url, query: &str) -> Url {
+    let mut url = url.clone();
+    let path = format!(
+        "{}%09{}",
+        url.path(),
+        utf8_percent_encode(query, DEFAULT_ENCODE_SET)
+    );
+    url.set_path(&path);
+    url
+}
+
+/// The menu one level up from `url`: the selector with its last part taken off, as a directory.
+///
+/// Returns `None` at the top of a server, or if `url` isn't a gopher URL.
+///
+/// ```
+/// use url::Url;
+///
+/// let url = Url::parse("gopher://example.com/0/phlog/2019/hello.txt").unwrap();
+/// let parent = gopher::parent_url(&url).unwrap();
+/// assert_eq!(parent.as_str(), "gopher://example.com/1/phlog/2019");
+/// ```
+pub fn parent_url(url: &Url) -> Option<Url> {
+    if url.scheme() != "gopher" {
+        return None;
+    }
+    let request = Request::from_url(url.clone()).ok()?;
+    // searches go up to the menu the index server is in, not to the index server itself
+    let selector = request.resource.split("%09").next().unwrap_or("");
+    let selector = selector.trim_end_matches('/');
+    if selector.is_empty() {
+        return None;
+    }
+
+    let mut parent = url.clone();
+    parent.set_query(None);
+    parent.set_fragment(None);
+    match selector.rfind('/') {
+        Some(end) => parent.set_path(&format!("/1/{}", &selector[..end])),
+        None => parent.set_path("/"),
+    }
+    Some(parent)
+}
+
+/// Encodes requests as a selector line, and decodes them on the server side.
+///
+/// Selector lines don't say what type of item they're for, so requests are decoded as menus, or
+/// as searches if they have a query, and it's up to the server to look at what the selector
+/// names.
+pub struct RequestCodec {
+    host: String,
+    port: u16,
+}
+
+impl RequestCodec {
+    pub fn new() -> Self {
+        RequestCodec::server("localhost", 70)
+    }
+
+    /// A codec for a server, which decodes requests into URLs on `host` and `port`.
+    pub fn server(host: impl Into<String>, port: u16) -> Self {
+        RequestCodec {
+            host: host.into(),
+            port,
+        }
+    }
+}
+
+impl Default for RequestCodec {
+    fn default() -> Self {
+        RequestCodec::new()
+    }
+}
+
+impl Encoder for RequestCodec {
+    type Item = Request;
+    type Error = Error;
+
+    fn encode(&mut self, item: Self::Item, bytes: &mut BytesMut) -> Result<(), Self::Error> {
+        bytes.extend_from_slice(&item.to_bytes());
+        Ok(())
+    }
+}
+
+impl Decoder for RequestCodec {
+    type Item = Request;
+    type Error = Error;
+
+    fn decode(&mut self, bytes: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let end = match bytes.iter().position(|b| *b == b'\n') {
+            Some(end) => end,
+            None if bytes.len() > MAX_LINE => {
+                let err = io::Error::new(io::ErrorKind::InvalidData, "request line is too long");
+                return Err(Error::IO(err));
+            }
+            None => return Ok(None),
+        };
+        let line = bytes.split_to(end + 1);
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        let mut parts = line.splitn(2, '\t');
+        let selector = parts.next().unwrap_or("");
+        let query = parts.next();
+        let item_type = match query {
+            Some(_) => ItemType::IndexServer,
+            None => ItemType::Dir,
+        };
+        let url = selector_url(&self.host, self.port, item_type, selector)
+            .ok_or_else(|| Error::InvalidUrl(line.to_owned()))?;
+        let url = match query {
+            Some(query) => search_url(&url, query),
+            None => url,
+        };
+        Request::with_default_port(url, self.port).map(Some)
+    }
+}
@@ -0,0 +1,294 @@
+use bytes::BytesMut;
+use encoding_rs::{Encoding, UTF_8};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::Serialize;
+use tokio::codec::Encoder;
+use url::percent_encoding::percent_decode;
+use url::Url;
+
+use crate::errors::Error;
+use crate::gophermap::Line;
+use crate::types::ItemType;
+
+/// A parsed response from a gopher server.
+#[derive(Debug)]
+pub struct Response {
+    pub url: Url,
+    /// The type of item that was requested, which decides how the body is parsed.
+    pub item_type: ItemType,
+    pub kind: ResponseKind,
+}
+
+#[derive(Debug)]
+pub enum ResponseKind {
+    /// A directory listing.
+    Menu(Vec<MenuEntry>),
+    TextFile(String),
+    BinaryFile(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub enum MenuEntry {
+    /// Consecutive info lines, joined with newlines.
+    Information(String),
+    /// A link: item type, display string and target URL.
+    Link(ItemType, String, String),
+}
+
+/// A single line of a menu, with the parts of links split out, as given in JSON output.
+#[derive(Debug, Serialize)]
+pub struct MenuLine {
+    #[serde(rename = "type")]
+    pub item_type: char,
+    pub display: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl MenuEntry {
+    /// The menu lines this entry was made from; information is split back into lines.
+    ///
+    /// The selector of a gopher link is taken from its URL, which doesn't keep whether the
+    /// server gave it with a leading slash, so it always has one: "phlog" and "/phlog" both come
+    /// back as "/phlog", and an empty selector as "/". Requests for the URL send it without the
+    /// slash either way. `URL:` selectors are the exception, they're given as they are.
+    pub fn lines(&self) -> Vec<MenuLine> {
+        match self {
+            // `lines` would drop a blank line at the end, or the only one
+            MenuEntry::Information(text) => text
+                .split('\n')
+                .map(|line| MenuLine {
+                    item_type: 'i',
+                    display: line.to_owned(),
+                    selector: None,
+                    host: None,
+                    port: None,
+                    url: None,
+                })
+                .collect(),
+            MenuEntry::Link(item_type, display, target) => {
+                // links to other schemes only have a URL
+                let url = Url::parse(target)
+                    .ok()
+                    .filter(|url| url.scheme() == "gopher");
+                // the path is the item type followed by the selector (RFC 4266)
+                let selector = url.as_ref().map(|url| {
+                    let path = url.path().get(2..).unwrap_or("");
+                    let selector = percent_decode(path.as_bytes()).decode_utf8_lossy();
+                    // "URL:" selectors link to other schemes, there's no slash before those
+                    match selector.get(1..) {
+                        Some(rest) if rest.starts_with("URL:") => rest.to_owned(),
+                        _ => selector.into_owned(),
+                    }
+                });
+                vec![MenuLine {
+                    item_type: item_type.encode() as char,
+                    display: display.clone(),
+                    selector,
+                    host: url
+                        .as_ref()
+                        .and_then(|url| url.host_str().map(str::to_owned)),
+                    port: url.as_ref().map(|url| url.port().unwrap_or(70)),
+                    url: Some(target.clone()),
+                }]
+            }
+        }
+    }
+}
+
+impl Response {
+    /// Parses a response, decoding text as UTF-8 with replacement characters for invalid bytes.
+    pub fn from_buf(url: Url, item_type: ItemType, buf: Vec<u8>) -> Result<Self, Error> {
+        Response::decode(url, item_type, buf, &[UTF_8])
+    }
+
+    /// Parses a response, decoding text with the first of `encodings` that it is valid in.
+    ///
+    /// If it isn't valid in any of them, the last one is used with replacement characters.
+    pub fn decode(
+        url: Url,
+        item_type: ItemType,
+        buf: Vec<u8>,
+        encodings: &[&'static Encoding],
+    ) -> Result<Self, Error> {
+        let kind = match item_type {
+            ItemType::File => ResponseKind::TextFile(decode_text(&buf, encodings)),
+            // search results are menus too
+            ItemType::Dir | ItemType::IndexServer => {
+                let string = decode_text(&buf, encodings);
+                let lines = string.lines();
+                let mut entries = Vec::new();
+                let mut current = Vec::new();
+
+                for line in lines {
+                    if line.is_empty() || line == "." {
+                        continue;
+                    }
+
+                    if is_bad_line(line) {
+                        error!("Bad line from server: {:?}", line);
+                        continue;
+                    }
+                    let line = Line::parse(line);
+
+                    // join the information strings together
+                    if line.is_info() {
+                        current.push(line.display);
+                        continue;
+                    } else if !current.is_empty() {
+                        entries.push(MenuEntry::Information(current.join("\n")));
+                        current.clear();
+                    }
+
+                    // links point at the host and port given in the menu line
+                    if let Some(link) = line.url() {
+                        entries.push(MenuEntry::Link(
+                            line.item_type,
+                            line.display,
+                            link.to_string(),
+                        ));
+                    }
+                }
+
+                if !current.is_empty() {
+                    entries.push(MenuEntry::Information(current.join("\n")));
+                }
+                ResponseKind::Menu(entries)
+            }
+            ItemType::BinHex
+            | ItemType::Dos
+            | ItemType::Uuencoded
+            | ItemType::Binary
+            | ItemType::Gif
+            | ItemType::Image => ResponseKind::BinaryFile(buf),
+            _ => return Err(Error::UnsupportedType(item_type)),
+        };
+        Ok(Response { url, item_type, kind })
+    }
+
+    /// The response as a server sends it: menus as menu lines ending with ".", files as they are.
+    ///
+    /// ```
+    /// # use gopher::{ItemType, Response};
+    /// # use url::Url;
+    /// let menu = "iHello\tfake\t(NULL)\t0\r\n1Phlog\t/phlog\texample.com\t70\r\n.\r\n";
+    /// let url = Url::parse("gopher://example.com/1/").unwrap();
+    /// let response = Response::from_buf(url, ItemType::Dir, menu.as_bytes().to_vec()).unwrap();
+    /// assert_eq!(response.to_bytes(), menu.as_bytes());
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let entries = match &self.kind {
+            ResponseKind::Menu(entries) => entries,
+            ResponseKind::TextFile(text) => return text.as_bytes().to_vec(),
+            ResponseKind::BinaryFile(buf) => return buf.clone(),
+        };
+
+        // links to other schemes are given as "URL:" selectors on this server
+        let host = self.url.host_str().unwrap_or("");
+        let port = self.url.port().unwrap_or(70);
+        let mut bytes = Vec::new();
+        for line in entries.iter().flat_map(MenuEntry::lines) {
+            let display = line.display.replace('\t', "    ");
+            let line = match (line.selector, line.host, line.port, line.url) {
+                (Some(selector), Some(host), Some(port), _) => format!(
+                    "{}{}\t{}\t{}\t{}\r\n",
+                    line.item_type, display, selector, host, port
+                ),
+                (_, _, _, Some(url)) => format!(
+                    "{}{}\tURL:{}\t{}\t{}\r\n",
+                    line.item_type, display, url, host, port
+                ),
+                _ => format!("{}{}\tfake\t(NULL)\t0\r\n", line.item_type, display),
+            };
+            bytes.extend_from_slice(line.as_bytes());
+        }
+        bytes.extend_from_slice(b".\r\n");
+        bytes
+    }
+}
+
+/// Menus are given as their lines, text files with their contents and binary files with only
+/// their size.
+impl Serialize for Response {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Response", 5)?;
+        state.serialize_field("url", self.url.as_str())?;
+        state.serialize_field("type", &(self.item_type.encode() as char))?;
+        match &self.kind {
+            ResponseKind::Menu(entries) => {
+                let lines = entries
+                    .iter()
+                    .flat_map(MenuEntry::lines)
+                    .collect::<Vec<_>>();
+                state.serialize_field("kind", "menu")?;
+                state.serialize_field("lines", &lines)?;
+            }
+            ResponseKind::TextFile(text) => {
+                state.serialize_field("kind", "text")?;
+                state.serialize_field("size", &text.len())?;
+                state.serialize_field("text", text)?;
+            }
+            ResponseKind::BinaryFile(buf) => {
+                state.serialize_field("kind", "binary")?;
+                state.serialize_field("size", &buf.len())?;
+            }
+        }
+        state.end()
+    }
+}
+
+/// Whether a line of a menu is left out for having fewer than four fields. Empty lines and the
+/// "." at the end are left out too, but aren't bad.
+///
+/// ```
+/// assert!(gopher::is_bad_line("just some text"));
+/// assert!(!gopher::is_bad_line("0About\t/about.txt\texample.com\t70"));
+/// assert!(!gopher::is_bad_line("."));
+/// ```
+pub fn is_bad_line(line: &str) -> bool {
+    !line.is_empty() && line != "." && line.split('\t').count() < 4
+}
+
+/// Decodes text with the first of `encodings` that it is valid in.
+pub fn decode_text(buf: &[u8], encodings: &[&'static Encoding]) -> String {
+    for encoding in encodings {
+        if let Some(text) = encoding.decode_without_bom_handling_and_without_replacement(buf) {
+            return text.into_owned();
+        }
+    }
+    let fallback = encodings.last().cloned().unwrap_or(UTF_8);
+    warn!(
+        "Text isn't valid {}, replacing bad characters",
+        fallback.name()
+    );
+    fallback.decode_without_bom_handling(buf).0.into_owned()
+}
+
+/// Encodes responses as a server sends them: menus as menu lines ending with ".", files as they
+/// are. There's no decoder, clients read the whole response and parse it with `Response::decode`.
+///
+/// The response says what type of item it is, so one codec works for all of them.
+#[derive(Default)]
+pub struct ResponseCodec;
+
+impl ResponseCodec {
+    pub fn new() -> Self {
+        ResponseCodec
+    }
+}
+
+impl Encoder for ResponseCodec {
+    type Item = Response;
+    type Error = Error;
+
+    fn encode(&mut self, item: Self::Item, bytes: &mut BytesMut) -> Result<(), Self::Error> {
+        bytes.extend_from_slice(&item.to_bytes());
+        Ok(())
+    }
+}
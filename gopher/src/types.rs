@@ -82,4 +82,38 @@ impl ItemType {
             Other(byte) => byte,
         }
     }
+
+    /// What the type is called, for showing to people.
+    ///
+    /// ```
+    /// use gopher::ItemType;
+    ///
+    /// assert_eq!(ItemType::Dir.name(), "directory");
+    /// assert_eq!(ItemType::Other(b'h').name(), "HTML file");
+    /// assert_eq!(ItemType::Other(b'x').name(), "unknown type x");
+    /// ```
+    pub fn name(self) -> String {
+        use ItemType::*;
+        let name = match self {
+            File => "text file",
+            Dir => "directory",
+            CsoServer => "CSO phone-book server",
+            Error => "error",
+            BinHex => "BinHex file",
+            Dos => "DOS binary",
+            Uuencoded => "uuencoded file",
+            IndexServer => "search",
+            Telnet => "telnet session",
+            Binary => "binary file",
+            RedundantServer => "redundant server",
+            Tn3270 => "tn3270 session",
+            Gif => "GIF image",
+            Image => "image",
+            Other(b'h') => "HTML file",
+            Other(b'i') => "information",
+            Other(b's') => "sound",
+            Other(byte) => return format!("unknown type {}", byte as char),
+        };
+        name.to_owned()
+    }
 }
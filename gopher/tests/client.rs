@@ -0,0 +1,149 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use gopher::{Client, Error, ItemType, Request, ResponseKind};
+use tokio::runtime::Runtime;
+use url::Url;
+
+/// Serves one connection, answering with `body` after `delay`, and gives the server's port.
+fn serve_once(body: &'static [u8], delay: Duration) -> (u16, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut selector = String::new();
+        BufReader::new(&stream).read_line(&mut selector).unwrap();
+        thread::sleep(delay);
+        let _ = stream.write_all(body);
+        selector
+    });
+    (port, handle)
+}
+
+fn request(port: u16, path: &str) -> Request {
+    let url = Url::parse(&format!("gopher://127.0.0.1:{}{}", port, path)).unwrap();
+    Request::from_url(url).unwrap()
+}
+
+#[test]
+fn fetches_text() {
+    let (port, server) = serve_once(b"hello, gopher\r\n", Duration::from_millis(0));
+    let mut runtime = Runtime::new().unwrap();
+    let response = runtime
+        .block_on(Client::request_async(request(port, "/0/hello.txt")))
+        .unwrap();
+
+    assert_eq!(server.join().unwrap(), "hello.txt\n");
+    match response.kind {
+        ResponseKind::TextFile(text) => assert_eq!(text, "hello, gopher\r\n"),
+        kind => panic!("not text: {:?}", kind),
+    }
+}
+
+#[test]
+fn fetches_raw_bytes() {
+    let (port, _server) = serve_once(b"\x00\x01\xff", Duration::from_millis(0));
+    let mut runtime = Runtime::new().unwrap();
+    let buf = runtime
+        .block_on(Client::fetch_raw(request(port, "/9/blob")))
+        .unwrap();
+    assert_eq!(buf, b"\x00\x01\xff".to_vec());
+}
+
+#[test]
+fn times_out() {
+    let (port, _server) = serve_once(b"too late", Duration::from_millis(500));
+    let mut runtime = Runtime::new().unwrap();
+    let result = runtime.block_on(Client::fetch_timeout(
+        request(port, "/0/slow"),
+        Duration::from_millis(50),
+    ));
+    match result {
+        Err(Error::Timeout(url)) => assert_eq!(url.port(), Some(port)),
+        other => panic!("expected a timeout: {:?}", other),
+    }
+}
+
+#[test]
+fn reports_progress() {
+    let (port, _server) = serve_once(b"a menu\r\n.\r\n", Duration::from_millis(0));
+    let mut runtime = Runtime::new().unwrap();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let progress = received.clone();
+    let buf = runtime
+        .block_on(Client::fetch_progress(
+            request(port, "/1/"),
+            Duration::from_secs(5),
+            move |bytes| progress.lock().unwrap().push(bytes),
+        ))
+        .unwrap();
+
+    assert_eq!(buf, b"a menu\r\n.\r\n".to_vec());
+    let received = received.lock().unwrap();
+    assert_eq!(received.last(), Some(&buf.len()));
+    assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn measures_requests() {
+    let (port, server) = serve_once(b"hello\r\n", Duration::from_millis(0));
+    let mut runtime = Runtime::new().unwrap();
+    let (buf, metrics) = runtime
+        .block_on(Client::fetch_metrics(
+            request(port, "/0/hello.txt"),
+            Duration::from_secs(5),
+            |_| (),
+        ))
+        .unwrap();
+
+    assert_eq!(metrics.request, server.join().unwrap().into_bytes());
+    assert_eq!(metrics.address.map(|addr| addr.port()), Some(port));
+    assert_eq!(metrics.received, buf.len());
+    let connected = metrics.connected.unwrap();
+    let first_byte = metrics.first_byte.unwrap();
+    assert!(connected <= first_byte && first_byte <= metrics.completed.unwrap());
+}
+
+#[test]
+fn measures_parsed_requests() {
+    let (port, _server) = serve_once(
+        b"iHello\tfake\t(NULL)\t0\r\n.\r\n",
+        Duration::from_millis(0),
+    );
+    let mut runtime = Runtime::new().unwrap();
+    let (response, metrics) = runtime
+        .block_on(Client::request_metrics(
+            request(port, "/1/"),
+            Duration::from_secs(5),
+        ))
+        .unwrap();
+
+    match response.kind {
+        ResponseKind::Menu(entries) => assert_eq!(entries.len(), 1),
+        kind => panic!("not a menu: {:?}", kind),
+    }
+    assert_eq!(metrics.item_type, ItemType::Dir);
+    assert!(metrics.completed.is_some());
+}
+
+#[test]
+fn measures_failed_requests() {
+    let (port, _server) = serve_once(b"too late", Duration::from_millis(500));
+    let mut runtime = Runtime::new().unwrap();
+    let result = runtime.block_on(Client::fetch_metrics(
+        request(port, "/0/slow"),
+        Duration::from_millis(50),
+        |_| (),
+    ));
+    match result {
+        Err((Error::Timeout(_), metrics)) => {
+            assert!(metrics.connected.is_some());
+            assert_eq!(metrics.first_byte, None);
+            assert_eq!(metrics.completed, None);
+        }
+        other => panic!("expected a timeout: {:?}", other),
+    }
+}
@@ -0,0 +1,93 @@
+use gopher::gophermap::{self, Line};
+use gopher::ItemType;
+
+#[test]
+fn parses_server_lines() {
+    let line = Line::parse("0About this server\t/about.txt\texample.com\t7070");
+    assert_eq!(line.item_type, ItemType::File);
+    assert_eq!(line.display, "About this server");
+    assert_eq!(line.selector, "/about.txt");
+    assert_eq!(line.host.as_deref(), Some("example.com"));
+    assert_eq!(line.port, Some(7070));
+    assert!(!line.is_info());
+    assert_eq!(
+        line.url().unwrap().as_str(),
+        "gopher://example.com:7070/0/about.txt"
+    );
+}
+
+#[test]
+fn lines_without_tabs_are_text() {
+    let line = Line::parse("Welcome to my hole");
+    assert!(line.is_info());
+    assert_eq!(line.display, "Welcome to my hole");
+    assert_eq!(line.url(), None);
+}
+
+#[test]
+fn only_i_lines_are_info() {
+    assert!(Line::parse("iHello\tfake\t(NULL)\t0").is_info());
+    let line = Line::parse("hWeb site\tURL:https://example.com/\texample.com\t70");
+    assert!(!line.is_info());
+    assert_eq!(line.item_type, ItemType::Other(b'h'));
+}
+
+#[test]
+fn local_links_have_no_url() {
+    let line = Line::parse("1Phlog\t/phlog");
+    assert_eq!(line.host, None);
+    assert_eq!(line.port, None);
+    assert_eq!(line.url(), None);
+}
+
+#[test]
+fn skips_empty_lines_and_terminator() {
+    let lines = gophermap::parse("iHello\t\t\t\n\n1Phlog\t/phlog\texample.com\t70\n.\n");
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].is_info());
+    assert_eq!(lines[1].item_type, ItemType::Dir);
+}
+
+#[test]
+fn display_round_trips() {
+    let text = "1Phlog\t/phlog\texample.com\t70";
+    assert_eq!(Line::parse(text).to_string(), text);
+    assert_eq!(
+        Line::parse("9Tarball\tfiles/a.tgz").to_string(),
+        "9Tarball\tfiles/a.tgz"
+    );
+}
+
+#[test]
+fn escapes_selectors() {
+    let line = Line::parse("0Is it?\t/faq/is it?.txt\texample.com\t70");
+    assert_eq!(
+        line.url().unwrap().as_str(),
+        "gopher://example.com/0/faq/is%20it%3F.txt"
+    );
+}
+
+#[test]
+fn lints_gophermaps() {
+    let text = "Welcome, this line is plain text\n\
+                1Phlog    /phlog\n\
+                \n\
+                0About\t/about.txt\texample.com\tseventy\n\
+                1Elsewhere\t/\tother.example\n\
+                xStrange\t/strange\n\
+                iInfo lines may have any port\tfake\t(NULL)\t0\n\
+                .\n";
+    let warnings = gophermap::lint(text)
+        .into_iter()
+        .map(|warning| warning.line)
+        .collect::<Vec<_>>();
+    assert_eq!(warnings, vec![2, 4, 5, 6]);
+}
+
+#[test]
+fn lints_long_lines() {
+    let long = "x".repeat(gophermap::MAX_WIDTH + 1);
+    assert_eq!(gophermap::lint(&long).len(), 1);
+    assert_eq!(gophermap::lint(&format!("0{}\t/a.txt", long)).len(), 1);
+    assert!(gophermap::lint(&"x".repeat(gophermap::MAX_WIDTH)).is_empty());
+}
@@ -0,0 +1,134 @@
+use bytes::BytesMut;
+use gopher::{ItemType, Request, RequestCodec};
+use tokio::codec::{Decoder, Encoder};
+use url::Url;
+
+fn request(url: &str) -> Request {
+    Request::from_url(Url::parse(url).unwrap()).unwrap()
+}
+
+#[test]
+fn splits_type_and_selector() {
+    let request = request("gopher://example.com:7070/1/phlog/2019");
+    assert_eq!(request.host, "example.com");
+    assert_eq!(request.port, 7070);
+    assert_eq!(request.item_type, ItemType::Dir);
+    assert_eq!(request.resource, "phlog/2019");
+}
+
+#[test]
+fn root_is_a_menu() {
+    let request = request("gopher://example.com");
+    assert_eq!(request.port, 70);
+    assert_eq!(request.item_type, ItemType::Dir);
+    assert_eq!(request.resource, "");
+}
+
+#[test]
+fn default_port() {
+    let url = Url::parse("gopher://example.com/0/a.txt").unwrap();
+    let request = Request::with_default_port(url, 7070).unwrap();
+    assert_eq!(request.port, 7070);
+}
+
+#[test]
+fn needs_a_host() {
+    let url = Url::parse("file:///0/a.txt").unwrap();
+    assert!(Request::from_url(url).is_err());
+}
+
+#[test]
+fn file_name() {
+    assert_eq!(
+        request("gopher://example.com/9/files/my%20archive.tgz").file_name(),
+        Some("my archive.tgz".to_owned())
+    );
+    assert_eq!(request("gopher://example.com/1/").file_name(), None);
+    assert_eq!(request("gopher://example.com/1/files/..").file_name(), None);
+}
+
+#[test]
+fn parent_url() {
+    let parent = |url: &str| gopher::parent_url(&Url::parse(url).unwrap()).map(|url| url.to_string());
+    assert_eq!(
+        parent("gopher://example.com:7070/1/phlog/2019/"),
+        Some("gopher://example.com:7070/1/phlog".to_owned())
+    );
+    assert_eq!(
+        parent("gopher://example.com/0/about.txt"),
+        Some("gopher://example.com/".to_owned())
+    );
+    assert_eq!(
+        parent("gopher://example.com/7/search/cats%09kittens"),
+        Some("gopher://example.com/1/search".to_owned())
+    );
+    assert_eq!(parent("gopher://example.com/1/"), None);
+    assert_eq!(parent("gopher://example.com"), None);
+    assert_eq!(parent("file:///home/docs"), None);
+}
+
+#[test]
+fn encodes_selector_line() {
+    let mut buf = BytesMut::new();
+    RequestCodec::new()
+        .encode(request("gopher://example.com/0/docs/readme.txt"), &mut buf)
+        .unwrap();
+    assert_eq!(&buf[..], b"docs/readme.txt\n");
+}
+
+#[test]
+fn encodes_search_with_a_tab() {
+    let url = gopher::search_url(
+        &Url::parse("gopher://example.com/7/search").unwrap(),
+        "cats & dogs",
+    );
+    let request = Request::from_url(url).unwrap();
+    assert_eq!(request.item_type, ItemType::IndexServer);
+
+    let mut buf = BytesMut::new();
+    RequestCodec::new().encode(request, &mut buf).unwrap();
+    assert_eq!(&buf[..], b"search\tcats & dogs\n");
+}
+
+#[test]
+fn decodes_escaped_selectors() {
+    let mut buf = BytesMut::new();
+    RequestCodec::new()
+        .encode(request("gopher://example.com/0/my%20notes.txt"), &mut buf)
+        .unwrap();
+    assert_eq!(&buf[..], b"my notes.txt\n");
+}
+
+#[test]
+fn decodes_selector_lines() {
+    let mut codec = RequestCodec::server("example.com", 7070);
+    let mut buf = BytesMut::from(&b"/phlog/my post?.txt\r\n"[..]);
+    let request = codec.decode(&mut buf).unwrap().unwrap();
+    assert!(buf.is_empty());
+    assert_eq!(request.host, "example.com");
+    assert_eq!(request.port, 7070);
+    assert_eq!(request.selector(), "phlog/my post?.txt");
+    assert_eq!(request.query(), None);
+}
+
+#[test]
+fn decodes_searches() {
+    let mut codec = RequestCodec::server("example.com", 70);
+    let mut buf = BytesMut::from(&b"/search\tcats and dogs\n"[..]);
+    let request = codec.decode(&mut buf).unwrap().unwrap();
+    assert_eq!(request.item_type, ItemType::IndexServer);
+    assert_eq!(request.selector(), "search");
+    assert_eq!(request.query(), Some("cats and dogs".to_owned()));
+}
+
+#[test]
+fn waits_for_the_whole_line() {
+    let mut codec = RequestCodec::server("example.com", 70);
+    let mut buf = BytesMut::from(&b"/phlog"[..]);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.extend_from_slice(b"\r\n");
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap().selector(), "phlog");
+
+    let mut buf = BytesMut::from(vec![b'a'; 5000]);
+    assert!(codec.decode(&mut buf).is_err());
+}
@@ -0,0 +1,261 @@
+use bytes::BytesMut;
+use encoding_rs::{UTF_8, WINDOWS_1252};
+use gopher::{decode_text, Error, ItemType, MenuEntry, Response, ResponseCodec, ResponseKind};
+use tokio::codec::Encoder;
+use url::Url;
+
+const MENU: &str = "iWelcome\tfake\t(NULL)\t0\r\n\
+                    iLinks below\tfake\t(NULL)\t0\r\n\
+                    1Phlog\t/phlog\texample.com\t70\r\n\
+                    0Notes\t/notes.txt\tother.net\t7070\r\n\
+                    .\r\n";
+
+fn url() -> Url {
+    Url::parse("gopher://example.com/1/").unwrap()
+}
+
+#[test]
+fn parses_menus() {
+    let response = Response::from_buf(url(), ItemType::Dir, MENU.as_bytes().to_vec()).unwrap();
+    let entries = match response.kind {
+        ResponseKind::Menu(entries) => entries,
+        kind => panic!("not a menu: {:?}", kind),
+    };
+
+    match &entries[0] {
+        MenuEntry::Information(text) => assert_eq!(text, "Welcome\nLinks below"),
+        entry => panic!("not information: {:?}", entry),
+    }
+    match &entries[1] {
+        MenuEntry::Link(ItemType::Dir, display, target) => {
+            assert_eq!(display, "Phlog");
+            assert_eq!(target, "gopher://example.com/1/phlog");
+        }
+        entry => panic!("not a menu link: {:?}", entry),
+    }
+    match &entries[2] {
+        MenuEntry::Link(ItemType::File, _, target) => {
+            assert_eq!(target, "gopher://other.net:7070/0/notes.txt");
+        }
+        entry => panic!("not a file link: {:?}", entry),
+    }
+}
+
+#[test]
+fn text_files() {
+    let response = Response::from_buf(url(), ItemType::File, b"hello\n".to_vec()).unwrap();
+    match response.kind {
+        ResponseKind::TextFile(text) => assert_eq!(text, "hello\n"),
+        kind => panic!("not text: {:?}", kind),
+    }
+}
+
+#[test]
+fn binary_files_are_kept() {
+    let response = Response::from_buf(url(), ItemType::Binary, vec![0, 159, 146]).unwrap();
+    match response.kind {
+        ResponseKind::BinaryFile(buf) => assert_eq!(buf, vec![0, 159, 146]),
+        kind => panic!("not binary: {:?}", kind),
+    }
+}
+
+#[test]
+fn unsupported_types() {
+    match Response::from_buf(url(), ItemType::Telnet, Vec::new()) {
+        Err(Error::UnsupportedType(ItemType::Telnet)) => (),
+        other => panic!("expected an error: {:?}", other),
+    }
+}
+
+#[test]
+fn falls_back_to_other_encodings() {
+    let latin1 = b"caf\xe9";
+    assert_eq!(decode_text(latin1, &[UTF_8, WINDOWS_1252]), "café");
+    assert_eq!(decode_text(latin1, &[UTF_8]), "caf\u{fffd}");
+    assert_eq!(
+        decode_text("café".as_bytes(), &[UTF_8, WINDOWS_1252]),
+        "café"
+    );
+}
+
+#[test]
+fn serializes_menu_lines() {
+    let response = Response::from_buf(url(), ItemType::Dir, MENU.as_bytes().to_vec()).unwrap();
+    let json = serde_json::to_value(&response).unwrap();
+    assert_eq!(json["url"], "gopher://example.com/1/");
+    assert_eq!(json["type"], "1");
+    assert_eq!(json["kind"], "menu");
+
+    let lines = json["lines"].as_array().unwrap();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[0],
+        serde_json::json!({ "type": "i", "display": "Welcome" })
+    );
+    assert_eq!(lines[2]["selector"], "/phlog");
+    assert_eq!(lines[2]["host"], "example.com");
+    assert_eq!(lines[2]["port"], 70);
+    assert_eq!(lines[3]["port"], 7070);
+}
+
+#[test]
+fn serializes_file_sizes() {
+    let response = Response::from_buf(url(), ItemType::Gif, vec![1, 2, 3]).unwrap();
+    let json = serde_json::to_value(&response).unwrap();
+    assert_eq!(json["kind"], "binary");
+    assert_eq!(json["size"], 3);
+    assert!(json.get("text").is_none());
+}
+
+#[test]
+fn skips_bad_lines() {
+    let menu = format!("a line without tabs\r\n{}", MENU);
+    let response = Response::from_buf(url(), ItemType::Dir, menu.into_bytes()).unwrap();
+    match response.kind {
+        ResponseKind::Menu(entries) => assert_eq!(entries.len(), 3),
+        kind => panic!("not a menu: {:?}", kind),
+    }
+    assert!(gopher::is_bad_line("a line without tabs"));
+    assert!(!gopher::is_bad_line(""));
+}
+
+#[test]
+fn keeps_information_at_the_end() {
+    let menu = format!("{}iGoodbye\tfake\t(NULL)\t0\r\n.\r\n", MENU.trim_end_matches(".\r\n"));
+    let response = Response::from_buf(url(), ItemType::Dir, menu.into_bytes()).unwrap();
+    let entries = match response.kind {
+        ResponseKind::Menu(entries) => entries,
+        kind => panic!("not a menu: {:?}", kind),
+    };
+    assert_eq!(entries.len(), 4);
+    match &entries[3] {
+        MenuEntry::Information(text) => assert_eq!(text, "Goodbye"),
+        entry => panic!("not information: {:?}", entry),
+    }
+}
+
+#[test]
+fn decodes_html_links() {
+    let menu = "hWeb site\tURL:https://example.com/\texample.com\t70\r\n.\r\n";
+    let response = Response::from_buf(url(), ItemType::Dir, menu.as_bytes().to_vec()).unwrap();
+    let entries = match response.kind {
+        ResponseKind::Menu(entries) => entries,
+        kind => panic!("not a menu: {:?}", kind),
+    };
+    match &entries[0] {
+        MenuEntry::Link(ItemType::Other(b'h'), display, target) => {
+            assert_eq!(display, "Web site");
+            assert_eq!(target, "gopher://example.com/h/URL:https://example.com/");
+        }
+        entry => panic!("not an HTML link: {:?}", entry),
+    }
+}
+
+#[test]
+fn search_results_are_menus() {
+    let response =
+        Response::from_buf(url(), ItemType::IndexServer, MENU.as_bytes().to_vec()).unwrap();
+    match response.kind {
+        ResponseKind::Menu(entries) => assert_eq!(entries.len(), 3),
+        kind => panic!("not a menu: {:?}", kind),
+    }
+}
+
+fn encode(response: Response) -> String {
+    let mut buf = BytesMut::new();
+    ResponseCodec::new()
+        .encode(response, &mut buf)
+        .unwrap();
+    String::from_utf8(buf.to_vec()).unwrap()
+}
+
+#[test]
+fn encodes_menus() {
+    let response = Response::from_buf(url(), ItemType::Dir, MENU.as_bytes().to_vec()).unwrap();
+    assert_eq!(
+        encode(response),
+        "iWelcome\tfake\t(NULL)\t0\r\n\
+         iLinks below\tfake\t(NULL)\t0\r\n\
+         1Phlog\t/phlog\texample.com\t70\r\n\
+         0Notes\t/notes.txt\tother.net\t7070\r\n\
+         .\r\n"
+    );
+}
+
+#[test]
+fn keeps_blank_information_lines() {
+    let menu = "i\tfake\t(NULL)\t0\r\n\
+                iHello\tfake\t(NULL)\t0\r\n\
+                i\tfake\t(NULL)\t0\r\n\
+                1Phlog\t/phlog\texample.com\t70\r\n\
+                i\tfake\t(NULL)\t0\r\n\
+                .\r\n";
+    let response = Response::from_buf(url(), ItemType::Dir, menu.as_bytes().to_vec()).unwrap();
+    assert_eq!(encode(response), menu);
+}
+
+#[test]
+fn encodes_links_to_other_schemes() {
+    let entries = vec![MenuEntry::Link(
+        ItemType::Other(b'h'),
+        "Web site".to_owned(),
+        "https://example.com/".to_owned(),
+    )];
+    let response = Response {
+        url: Url::parse("gopher://example.com:7070/1/").unwrap(),
+        item_type: ItemType::Dir,
+        kind: ResponseKind::Menu(entries),
+    };
+    assert_eq!(
+        encode(response),
+        "hWeb site\tURL:https://example.com/\texample.com\t7070\r\n.\r\n"
+    );
+}
+
+#[test]
+fn encodes_files_as_they_are() {
+    let response = Response::from_buf(url(), ItemType::Binary, vec![0, 1, 2]).unwrap();
+    assert_eq!(encode(response).as_bytes(), &[0, 1, 2]);
+}
+
+#[test]
+fn selectors_get_a_leading_slash() {
+    let menu = "1Phlog\tphlog\texample.com\t70\r\n1Home\t\texample.com\t70\r\n.\r\n";
+    let response = Response::from_buf(url(), ItemType::Dir, menu.as_bytes().to_vec()).unwrap();
+    let entries = match &response.kind {
+        ResponseKind::Menu(entries) => entries,
+        kind => panic!("not a menu: {:?}", kind),
+    };
+    let selectors = entries
+        .iter()
+        .flat_map(MenuEntry::lines)
+        .map(|line| line.selector)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        selectors,
+        vec![Some("/phlog".to_owned()), Some("/".to_owned())]
+    );
+    assert_eq!(
+        encode(response),
+        "1Phlog\t/phlog\texample.com\t70\r\n1Home\t/\texample.com\t70\r\n.\r\n"
+    );
+}
+
+#[test]
+fn keeps_url_selectors() {
+    let menu = "hWeb site\tURL:https://example.com/\texample.com\t70\r\n.\r\n";
+    let entries = vec![MenuEntry::Link(
+        ItemType::Other(b'h'),
+        "Web site".to_owned(),
+        gopher::gophermap::Line::parse(menu.lines().next().unwrap())
+            .url()
+            .unwrap()
+            .to_string(),
+    )];
+    let response = Response {
+        url: url(),
+        item_type: ItemType::Dir,
+        kind: ResponseKind::Menu(entries),
+    };
+    assert_eq!(encode(response), menu);
+}
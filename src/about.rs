@@ -0,0 +1,78 @@
+use std::time::SystemTime;
+
+use gopher::{ItemType, MenuEntry, Response, ResponseKind};
+use url::Url;
+
+use crate::bookmarks::Bookmarks;
+use crate::cache::Cache;
+use crate::history::History;
+
+/// Builds the page for an `about:` URL, or `None` if there is no such page.
+pub fn page(
+    url: &Url,
+    cache: &Cache,
+    bookmarks: &Bookmarks,
+    history: &History,
+) -> Option<Response> {
+    let entries = match url.path() {
+        "bookmarks" => bookmarks.to_menu(),
+        "cache" => cache_entries(cache),
+        "history" => {
+            let query = url
+                .query_pairs()
+                .find(|(key, _)| key == "q")
+                .map(|(_, value)| value.into_owned());
+            history.to_menu(query.as_ref().map(String::as_str))
+        }
+        _ => return None,
+    };
+
+    Some(Response {
+        url: url.clone(),
+        item_type: ItemType::Dir,
+        kind: ResponseKind::Menu(entries),
+    })
+}
+
+fn cache_entries(cache: &Cache) -> Vec<MenuEntry> {
+    let list = cache.list();
+    let total = list.iter().map(|info| info.size).sum::<usize>();
+
+    let mut entries = vec![MenuEntry::Information(format!(
+        "{} cached responses, {} total",
+        list.len(),
+        format_size(total)
+    ))];
+    for info in list {
+        let label = format!(
+            "{} ({}, fetched {}{})",
+            info.url,
+            format_size(info.size),
+            format_age(info.fetched),
+            if info.in_memory { "" } else { ", on disk" }
+        );
+        entries.push(MenuEntry::Link(info.item_type, label, info.url.to_string()));
+    }
+    entries
+}
+
+pub fn format_size(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{} B", bytes),
+        1024..=1_048_575 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+pub fn format_age(time: SystemTime) -> String {
+    let secs = match SystemTime::now().duration_since(time) {
+        Ok(age) => age.as_secs(),
+        Err(_) => return "just now".to_owned(),
+    };
+    match secs {
+        0..=59 => "just now".to_owned(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
@@ -0,0 +1,249 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use structopt::StructOpt;
+use url::Url;
+
+use crate::check::EXIT_CODES as CHECK_EXIT_CODES;
+use crate::fetch::EXIT_CODES;
+
+/// A GTK3-based browser for Gopherspace.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "gopher-browser")]
+pub struct Args {
+    /// URLs to open, each in its own tab; "gopher://" may be left out
+    #[structopt(name = "URL", parse(try_from_str = "parse_url"))]
+    pub urls: Vec<Url>,
+
+    /// Open a new window even if one is already running with this profile
+    #[structopt(long = "new-window")]
+    pub new_window: bool,
+
+    /// Keep config, bookmarks, history and cache in DIR instead of the usual places
+    #[structopt(long = "profile", name = "DIR", parse(from_os_str))]
+    pub profile: Option<PathBuf>,
+
+    /// Don't record history or write the cache to disk
+    #[structopt(long = "private")]
+    pub private: bool,
+
+    /// Browse in the terminal instead of opening a window
+    #[structopt(long = "tui")]
+    pub tui: bool,
+
+    /// Read settings from FILE instead of the profile's config.toml
+    #[structopt(long = "config", name = "FILE", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Print a gopher resource without opening a window
+    #[structopt(name = "fetch", raw(after_help = "EXIT_CODES"))]
+    Fetch(FetchArgs),
+
+    /// Serve the files in a directory over gopher
+    #[structopt(name = "serve")]
+    Serve(ServeArgs),
+
+    /// Check the links on a menu for broken ones
+    #[structopt(name = "check", raw(after_help = "CHECK_EXIT_CODES"))]
+    Check(CheckArgs),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct FetchArgs {
+    /// URL to fetch; "gopher://" may be left out
+    #[structopt(name = "URL", parse(try_from_str = "parse_url"))]
+    pub url: Url,
+
+    /// Print the response exactly as the server sent it
+    #[structopt(long = "raw")]
+    pub raw: bool,
+
+    /// Print menus and text files in this format instead of as text; ignored with --raw
+    #[structopt(
+        long = "format",
+        default_value = "text",
+        raw(possible_values = "FETCH_FORMATS")
+    )]
+    pub format: Format,
+
+    /// Save binary files to FILE instead of a file named after the selector
+    #[structopt(short = "o", long = "output", name = "FILE", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ServeArgs {
+    /// Directory to serve; directories with a gophermap file are served as that menu
+    #[structopt(name = "DIR", parse(from_os_str))]
+    pub dir: PathBuf,
+
+    /// Host name to listen on and to give in menus
+    #[structopt(long = "host", default_value = "localhost")]
+    pub host: String,
+
+    /// Port to listen on
+    #[structopt(short = "p", long = "port", default_value = "7070")]
+    pub port: u16,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CheckArgs {
+    /// Menu to check; "gopher://" may be left out, and local gophermaps can be given as file URLs
+    #[structopt(name = "URL", parse(try_from_str = "parse_url"))]
+    pub url: Url,
+
+    /// Print the problems found as "text" or "json"
+    #[structopt(
+        long = "format",
+        default_value = "text",
+        raw(possible_values = r#"&["text", "json"]"#)
+    )]
+    pub format: Format,
+
+    /// How many links on the same server to check at once
+    #[structopt(long = "per-host", name = "N", default_value = "4")]
+    pub per_host: usize,
+
+    /// Also print the links that are fine or weren't checked
+    #[structopt(long = "all")]
+    pub all: bool,
+}
+
+const FETCH_FORMATS: &[&str] = &[
+    "text",
+    "json",
+    "html",
+    "ansi",
+    "gophermap",
+    "markdown",
+    "gemtext",
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Html,
+    Ansi,
+    Gophermap,
+    Markdown,
+    Gemtext,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "html" => Ok(Format::Html),
+            "ansi" => Ok(Format::Ansi),
+            "gophermap" => Ok(Format::Gophermap),
+            "markdown" => Ok(Format::Markdown),
+            "gemtext" => Ok(Format::Gemtext),
+            _ => Err(format!("unknown format {:?}", s)),
+        }
+    }
+}
+
+/// Parses a URL from the command line or the address bar.
+///
+/// Anything without a scheme is a gopher address like "example.org:7070/1/phlog", where paths
+/// that don't start with an item type are taken to be menus. URLs with a scheme are kept as they
+/// are, and absolute paths are local files.
+pub fn parse_url(arg: &str) -> Result<Url, String> {
+    let arg = arg.trim();
+    if arg.is_empty() {
+        return Err("no address given".to_owned());
+    }
+    if arg.starts_with('/') {
+        return Url::from_file_path(arg).map_err(|()| format!("{:?} is not a valid path", arg));
+    }
+
+    // "host:port" would parse as a URL with the scheme "host"
+    let has_scheme = arg.contains("://") || arg.starts_with("about:");
+    let url = if has_scheme {
+        Url::parse(arg)
+    } else {
+        Url::parse(&format!("gopher://{}", arg))
+    };
+    let mut url = url.map_err(|err| format!("{:?} is not a valid address: {}", arg, err))?;
+    match url.scheme() {
+        "gopher" => (),
+        "file" | "about" => return Ok(url),
+        scheme => {
+            return Err(format!(
+                "{} URLs can't be opened, only gopher, file and about ones",
+                scheme
+            ))
+        }
+    }
+    if url.host_str().map_or(true, str::is_empty) {
+        return Err(format!("{:?} has no host", arg));
+    }
+    if url.port() == Some(0) {
+        return Err(format!("{:?}: port 0 can't be connected to", arg));
+    }
+
+    // the item type is the first part of the path (RFC 4266), so "/phlog" would be type "p"
+    let has_type = has_scheme
+        || url
+            .path_segments()
+            .and_then(|mut segments| segments.next())
+            .map_or(true, |first| first.chars().count() <= 1);
+    if !has_type {
+        let path = format!("/1{}", url.path());
+        url.set_path(&path);
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(arg: &str) -> String {
+        parse_url(arg).unwrap().to_string()
+    }
+
+    #[test]
+    fn adds_the_scheme_and_item_type() {
+        assert_eq!(parsed("example.org"), "gopher://example.org/");
+        assert_eq!(
+            parsed("example.org:7070/phlog"),
+            "gopher://example.org:7070/1/phlog"
+        );
+        assert_eq!(
+            parsed(" example.org/0/notes.txt "),
+            "gopher://example.org/0/notes.txt"
+        );
+    }
+
+    #[test]
+    fn keeps_urls_with_a_scheme() {
+        assert_eq!(
+            parsed("gopher://example.org/phlog"),
+            "gopher://example.org/phlog"
+        );
+        assert_eq!(
+            parsed("gopher://example.org/1/phlog"),
+            "gopher://example.org/1/phlog"
+        );
+        assert_eq!(parsed("about:bookmarks"), "about:bookmarks");
+        assert_eq!(parsed("/tmp/gophermap"), "file:///tmp/gophermap");
+    }
+
+    #[test]
+    fn rejects_bad_addresses() {
+        assert!(parse_url("").is_err());
+        assert!(parse_url("https://example.org/").is_err());
+        assert!(parse_url("example.org:0").is_err());
+    }
+}
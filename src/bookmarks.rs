@@ -0,0 +1,323 @@
+use std::fs;
+use std::path::PathBuf;
+
+use gopher::gophermap::Line;
+use gopher::{ItemType, MenuEntry, Request};
+use url::Url;
+
+use crate::cache::normalize;
+use crate::errors::Error;
+
+/// Prefix of the info lines that start a folder in the bookmarks file.
+const FOLDER_PREFIX: &str = "# ";
+/// Host used for info lines, so that the file is a valid gopher menu.
+const NULL_HOST: &str = "null.host";
+
+#[derive(Clone, Debug)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: Url,
+    pub item_type: ItemType,
+    pub folder: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// The user's bookmarks, stored as a gophermap.
+///
+/// Folders are info lines starting with `# `, followed by the bookmarks in them. Tags are kept
+/// in a fifth, comma separated field that other clients ignore. Links that aren't gopher URLs
+/// use the `URL:` selector convention.
+pub struct Bookmarks {
+    path: Option<PathBuf>,
+    entries: Vec<Bookmark>,
+    /// The port of URLs without one, for telling whether two URLs are the same.
+    default_port: u16,
+}
+
+impl Bookmarks {
+    /// Loads the bookmarks from `path`; a missing file means there are no bookmarks yet.
+    pub fn load(path: Option<PathBuf>, default_port: u16) -> Self {
+        let entries = match &path {
+            Some(path) if path.exists() => match fs::read_to_string(path) {
+                Ok(text) => parse(&text),
+                Err(err) => {
+                    error!("Could not read bookmarks from {:?}: {}", path, err);
+                    Vec::new()
+                }
+            },
+            _ => Vec::new(),
+        };
+        Bookmarks {
+            path,
+            entries,
+            default_port,
+        }
+    }
+
+    pub fn set_default_port(&mut self, default_port: u16) {
+        self.default_port = default_port;
+    }
+
+    pub fn entries(&self) -> &[Bookmark] {
+        &self.entries
+    }
+
+    /// The folder names in the order they first appear, starting with `None` for the top level.
+    pub fn folders(&self) -> Vec<Option<&str>> {
+        let mut folders = vec![None];
+        for bookmark in &self.entries {
+            let folder = bookmark.folder.as_ref().map(String::as_str);
+            if !folders.contains(&folder) {
+                folders.push(folder);
+            }
+        }
+        folders
+    }
+
+    pub fn contains(&self, url: &Url) -> bool {
+        let key = normalize(url, self.default_port);
+        self.entries
+            .iter()
+            .any(|bookmark| normalize(&bookmark.url, self.default_port) == key)
+    }
+
+    /// Adds a bookmark, replacing any existing one for the same URL.
+    pub fn add(&mut self, bookmark: Bookmark) -> Result<(), Error> {
+        let port = self.default_port;
+        let key = normalize(&bookmark.url, port);
+        self.entries
+            .retain(|existing| normalize(&existing.url, port) != key);
+        self.entries.push(bookmark);
+        self.save()
+    }
+
+    pub fn remove(&mut self, url: &Url) -> Result<(), Error> {
+        let port = self.default_port;
+        let key = normalize(url, port);
+        self.entries
+            .retain(|bookmark| normalize(&bookmark.url, port) != key);
+        self.save()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_gophermap())?;
+        Ok(())
+    }
+
+    pub fn to_gophermap(&self) -> String {
+        let mut text = String::new();
+        for folder in self.folders() {
+            if let Some(name) = folder {
+                text.push_str(&info_line(&format!("{}{}", FOLDER_PREFIX, name)));
+            }
+            for bookmark in self.in_folder(folder) {
+                text.push_str(&bookmark_line(bookmark));
+            }
+        }
+        text.push_str(".\r\n");
+        text
+    }
+
+    /// The menu shown on `about:bookmarks`.
+    pub fn to_menu(&self) -> Vec<MenuEntry> {
+        let mut entries = Vec::new();
+        if self.entries.is_empty() {
+            entries.push(MenuEntry::Information(
+                "No bookmarks yet. Press Ctrl+D to bookmark a page.".to_owned(),
+            ));
+        }
+        for folder in self.folders() {
+            if let Some(name) = folder {
+                entries.push(MenuEntry::Information(format!("\n{}", name)));
+            }
+            for bookmark in self.in_folder(folder) {
+                let label = if bookmark.tags.is_empty() {
+                    bookmark.title.clone()
+                } else {
+                    format!("{} [{}]", bookmark.title, bookmark.tags.join(", "))
+                };
+                entries.push(MenuEntry::Link(
+                    bookmark.item_type,
+                    label,
+                    bookmark.url.to_string(),
+                ));
+            }
+        }
+        entries
+    }
+
+    pub fn in_folder<'a>(&'a self, folder: Option<&'a str>) -> impl Iterator<Item = &'a Bookmark> {
+        self.entries
+            .iter()
+            .filter(move |bookmark| bookmark.folder.as_ref().map(String::as_str) == folder)
+    }
+}
+
+/// A default title for a page, since gopher doesn't have titles.
+pub fn default_title(url: &Url) -> String {
+    // the first segment is the item type
+    let segments = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    match (segments.len(), url.host_str()) {
+        (n, _) if n > 1 => segments[n - 1].to_owned(),
+        (_, Some(host)) => host.to_owned(),
+        _ => url.to_string(),
+    }
+}
+
+fn info_line(text: &str) -> String {
+    format!("i{}\t\t{}\t1\r\n", field(text), NULL_HOST)
+}
+
+/// Text that can go in a field of a menu line: tabs and line breaks would start another field
+/// or line.
+fn field(text: &str) -> String {
+    text.replace('\t', "    ")
+        .replace(|c| c == '\r' || c == '\n', " ")
+}
+
+fn bookmark_line(bookmark: &Bookmark) -> String {
+    let line = match Request::from_url(bookmark.url.clone()) {
+        Ok(ref request) if bookmark.url.scheme() == "gopher" => Line {
+            item_type: request.item_type,
+            display: field(&bookmark.title),
+            selector: request.resource.clone(),
+            host: Some(request.host.clone()),
+            port: Some(request.port),
+        },
+        _ => Line {
+            item_type: ItemType::Other(b'h'),
+            display: field(&bookmark.title),
+            selector: format!("URL:{}", bookmark.url),
+            host: Some(NULL_HOST.to_owned()),
+            port: Some(1),
+        },
+    };
+    format!("{}\t{}\r\n", line, field(&bookmark.tags.join(",")))
+}
+
+fn parse(text: &str) -> Vec<Bookmark> {
+    let mut folder = None;
+    let mut entries = Vec::new();
+    for raw in text.lines().filter(|line| !line.is_empty() && *line != ".") {
+        let line = Line::parse(raw);
+        if line.is_info() {
+            if line.display.starts_with(FOLDER_PREFIX) {
+                folder = Some(line.display[FOLDER_PREFIX.len()..].to_owned());
+            }
+            continue;
+        }
+
+        let url = if line.selector.starts_with("URL:") {
+            Url::parse(&line.selector[4..]).ok()
+        } else {
+            line.url()
+        };
+        let url = match url {
+            Some(url) => url,
+            None => {
+                warn!("Bad bookmark line: {:?}", raw);
+                continue;
+            }
+        };
+        let tags = raw
+            .split('\t')
+            .nth(4)
+            .map(|tags| {
+                tags.split(',')
+                    .map(|tag| tag.trim().to_owned())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        entries.push(Bookmark {
+            title: line.display,
+            url,
+            item_type: line.item_type,
+            folder: folder.clone(),
+            tags,
+        });
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmark(title: &str, url: &str, folder: Option<&str>, tags: &[&str]) -> Bookmark {
+        let url = Url::parse(url).unwrap();
+        let item_type = match Request::from_url(url.clone()) {
+            Ok(request) if url.scheme() == "gopher" => request.item_type,
+            _ => ItemType::Other(b'h'),
+        };
+        Bookmark {
+            title: title.to_owned(),
+            url,
+            item_type,
+            folder: folder.map(str::to_owned),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let bookmarks = Bookmarks {
+            path: None,
+            default_port: 70,
+            entries: vec![
+                bookmark("Floodgap", "gopher://gopher.floodgap.com/1/", None, &[]),
+                bookmark(
+                    "Phlog",
+                    "gopher://example.com/1/phlog",
+                    Some("Blogs"),
+                    &["daily"],
+                ),
+                bookmark("Web", "https://example.com/", Some("Blogs"), &["a", "b"]),
+            ],
+        };
+        let parsed = parse(&bookmarks.to_gophermap());
+        assert_eq!(parsed.len(), 3);
+        for (parsed, bookmark) in parsed.iter().zip(bookmarks.entries()) {
+            assert_eq!(parsed.title, bookmark.title);
+            assert_eq!(parsed.url, bookmark.url);
+            assert_eq!(parsed.item_type, bookmark.item_type);
+            assert_eq!(parsed.folder, bookmark.folder);
+            assert_eq!(parsed.tags, bookmark.tags);
+        }
+    }
+
+    #[test]
+    fn tabs_and_newlines_dont_break_the_file() {
+        let bookmarks = Bookmarks {
+            path: None,
+            default_port: 70,
+            entries: vec![
+                bookmark("Two\r\nlines", "gopher://example.com/0/a", None, &["x\ty"]),
+                bookmark(
+                    "Tab\there",
+                    "gopher://example.com/0/b",
+                    Some("A\nfolder"),
+                    &[],
+                ),
+            ],
+        };
+        let parsed = parse(&bookmarks.to_gophermap());
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].title, "Two  lines");
+        assert_eq!(parsed[0].url.as_str(), "gopher://example.com/0/a");
+        assert_eq!(parsed[0].tags, vec!["x    y".to_owned()]);
+        assert_eq!(parsed[1].title, "Tab    here");
+        assert_eq!(parsed[1].folder, Some("A folder".to_owned()));
+    }
+}
@@ -0,0 +1,499 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use encoding_rs::Encoding;
+use gopher::{ItemType, Response};
+use url::Url;
+
+use crate::errors::Error;
+
+/// Number of responses kept in memory by default.
+pub const DEFAULT_CAPACITY: usize = 64;
+/// Megabytes of responses kept on disk by default.
+pub const DEFAULT_DISK_LIMIT_MIB: u64 = 100;
+
+/// A cache of raw gopher responses, keyed by normalized URL.
+///
+/// The most recently used entries are kept in memory; if a directory is given, every entry is
+/// also written to disk so it survives restarts, and the oldest files are removed once the
+/// directory holds more than its limit.
+///
+/// In offline mode, requests are answered only from the cache, regardless of the age of the
+/// stored entries.
+pub struct Cache {
+    capacity: usize,
+    entries: HashMap<String, Entry>,
+    // least recently used key first
+    order: VecDeque<String>,
+    disk: Option<PathBuf>,
+    max_disk_bytes: u64,
+    /// The port of URLs without one, which requests use too.
+    default_port: u16,
+    offline: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub url: Url,
+    pub item_type: ItemType,
+    pub body: Vec<u8>,
+    pub fetched: SystemTime,
+}
+
+/// Summary of a cached entry, used for the cache inspection page.
+#[derive(Debug)]
+pub struct EntryInfo {
+    pub url: Url,
+    pub item_type: ItemType,
+    pub size: usize,
+    pub fetched: SystemTime,
+    pub in_memory: bool,
+}
+
+impl Entry {
+    /// The entry as the response to a request for `url`. Entries are shared by URLs that
+    /// normalize to the same key, so the response is given the URL that was asked for rather
+    /// than the one the entry was stored under.
+    pub fn to_response(
+        &self,
+        url: Url,
+        item_type: ItemType,
+        encodings: &[&'static Encoding],
+    ) -> Result<Response, Error> {
+        Response::decode(url, item_type, self.body.clone(), encodings).map_err(Error::from)
+    }
+
+    /// Whether this entry is still fresh according to the TTL of its item type.
+    pub fn is_fresh(&self) -> bool {
+        match SystemTime::now().duration_since(self.fetched) {
+            Ok(age) => age < ttl(self.item_type),
+            // fetched "in the future", the clock must have changed
+            Err(_) => false,
+        }
+    }
+}
+
+/// How long a response of the given type is considered fresh.
+pub fn ttl(item_type: ItemType) -> Duration {
+    match item_type {
+        // menus change often (phlogs, news feeds)
+        ItemType::Dir => Duration::from_secs(10 * 60),
+        ItemType::File => Duration::from_secs(60 * 60),
+        // search results depend on the query and are never reused
+        ItemType::IndexServer | ItemType::CsoServer | ItemType::Error => Duration::from_secs(0),
+        _ => Duration::from_secs(24 * 60 * 60),
+    }
+}
+
+/// Normalizes a gopher URL into the key used by the cache.
+///
+/// The host is lowercased, `default_port` is made explicit, and an empty path is treated as the
+/// root menu, so that `gopher://Example.org` and `gopher://example.org:70/1/` are the same entry
+/// when the default port is 70.
+pub fn normalize(url: &Url, default_port: u16) -> String {
+    if url.scheme() != "gopher" {
+        return url.to_string();
+    }
+
+    let host = url.host_str().unwrap_or("").to_lowercase();
+    let port = url.port().unwrap_or(default_port);
+    let path = url.path().trim_start_matches('/');
+    let (ty, selector) = if path.is_empty() {
+        ('1', "")
+    } else {
+        let mut chars = path.chars();
+        let ty = chars.next().unwrap();
+        (ty, chars.as_str().trim_start_matches('/'))
+    };
+
+    let mut key = format!("gopher://{}:{}/{}/{}", host, port, ty, selector);
+    if let Some(query) = url.query() {
+        key.push('?');
+        key.push_str(query);
+    }
+    key
+}
+
+impl Cache {
+    pub fn new(
+        capacity: usize,
+        disk: Option<PathBuf>,
+        max_disk_bytes: u64,
+        default_port: u16,
+    ) -> Self {
+        if let Some(dir) = &disk {
+            if let Err(err) = fs::create_dir_all(dir) {
+                error!("Could not create cache directory {:?}: {}", dir, err);
+            }
+        }
+
+        Cache {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            disk,
+            max_disk_bytes,
+            default_port,
+            offline: false,
+        }
+    }
+
+    pub fn set_default_port(&mut self, default_port: u16) {
+        self.default_port = default_port;
+    }
+
+    fn key(&self, url: &Url) -> String {
+        normalize(url, self.default_port)
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Whether there is an entry for the URL, without loading it.
+    pub fn contains(&self, url: &Url) -> bool {
+        let key = self.key(url);
+        self.entries.contains_key(&key)
+            || self
+                .disk_path(&key)
+                .map(|path| path.exists())
+                .unwrap_or(false)
+    }
+
+    /// Looks up a fresh entry for the URL, in memory first and then on disk.
+    pub fn get(&mut self, url: &Url) -> Option<Entry> {
+        let entry = self.get_any(url)?;
+        if entry.is_fresh() {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Looks up an entry for the URL regardless of its age.
+    pub fn get_any(&mut self, url: &Url) -> Option<Entry> {
+        let key = self.key(url);
+        if let Some(entry) = self.entries.get(&key).cloned() {
+            self.touch(&key);
+            return Some(entry);
+        }
+
+        let entry = self.read_disk(&key)?;
+        self.insert_memory(key, entry.clone());
+        Some(entry)
+    }
+
+    pub fn insert(&mut self, url: Url, item_type: ItemType, body: Vec<u8>) {
+        if ttl(item_type) == Duration::from_secs(0) {
+            return;
+        }
+
+        let key = self.key(&url);
+        let entry = Entry {
+            url,
+            item_type,
+            body,
+            fetched: SystemTime::now(),
+        };
+        if let Err(err) = self.write_disk(&key, &entry) {
+            error!("Could not write cache entry for {}: {}", key, err);
+        }
+        if let Err(err) = self.trim_disk() {
+            error!("Could not trim the cache directory: {}", err);
+        }
+        self.insert_memory(key, entry);
+    }
+
+    /// Removes the entry for the URL from memory and disk.
+    pub fn invalidate(&mut self, url: &Url) {
+        let key = self.key(url);
+        self.entries.remove(&key);
+        self.order.retain(|k| k != &key);
+        if let Some(path) = self.disk_path(&key) {
+            if path.exists() {
+                if let Err(err) = fs::remove_file(&path) {
+                    error!("Could not remove cache file {:?}: {}", path, err);
+                }
+            }
+        }
+    }
+
+    /// Lists every stored entry, most recently used first, followed by entries only on disk.
+    pub fn list(&self) -> Vec<EntryInfo> {
+        let mut list = self
+            .order
+            .iter()
+            .rev()
+            .filter_map(|key| self.entries.get(key))
+            .map(|entry| EntryInfo {
+                url: entry.url.clone(),
+                item_type: entry.item_type,
+                size: entry.body.len(),
+                fetched: entry.fetched,
+                in_memory: true,
+            })
+            .collect::<Vec<_>>();
+
+        let dir = match &self.disk {
+            Some(dir) => dir,
+            None => return list,
+        };
+        let files = match fs::read_dir(dir) {
+            Ok(files) => files,
+            Err(err) => {
+                error!("Could not read cache directory {:?}: {}", dir, err);
+                return list;
+            }
+        };
+        for file in files.filter_map(|file| file.ok()) {
+            let path = file.path();
+            let (url, item_type, fetched, size) = match read_header(&path) {
+                Ok(header) => header,
+                Err(err) => {
+                    warn!("Skipping cache file {:?}: {}", path, err);
+                    continue;
+                }
+            };
+            if self.entries.contains_key(&self.key(&url)) {
+                continue;
+            }
+            list.push(EntryInfo {
+                url,
+                item_type,
+                size,
+                fetched,
+                in_memory: false,
+            });
+        }
+        list
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(pos).unwrap();
+            self.order.push_back(key);
+        }
+    }
+
+    fn insert_memory(&mut self, key: String, entry: Entry) {
+        if self.entries.insert(key.clone(), entry).is_some() {
+            self.touch(&key);
+            return;
+        }
+
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn disk_path(&self, key: &str) -> Option<PathBuf> {
+        self.disk
+            .as_ref()
+            .map(|dir| dir.join(format!("{:016x}", fnv1a(key.as_bytes()))))
+    }
+
+    fn write_disk(&self, key: &str, entry: &Entry) -> io::Result<()> {
+        let path = match self.disk_path(key) {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let fetched = entry
+            .fetched
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", entry.url)?;
+        writeln!(file, "{}", entry.item_type.encode() as char)?;
+        writeln!(file, "{}", fetched)?;
+        file.write_all(&entry.body)
+    }
+
+    /// Removes the files written longest ago until the directory fits in `max_disk_bytes`.
+    fn trim_disk(&self) -> io::Result<()> {
+        let dir = match &self.disk {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        let mut files = fs::read_dir(dir)?
+            .filter_map(|file| file.ok())
+            .filter_map(|file| {
+                let metadata = file.metadata().ok().filter(|metadata| metadata.is_file())?;
+                Some((metadata.modified().ok()?, metadata.len(), file.path()))
+            })
+            .collect::<Vec<_>>();
+        let mut total = files.iter().map(|(_, len, _)| len).sum::<u64>();
+        files.sort();
+        for (_, len, path) in files {
+            if total <= self.max_disk_bytes {
+                break;
+            }
+            fs::remove_file(&path)?;
+            total -= len;
+        }
+        Ok(())
+    }
+
+    fn read_disk(&self, key: &str) -> Option<Entry> {
+        let path = self.disk_path(key)?;
+        if !path.exists() {
+            return None;
+        }
+
+        let result = File::open(&path).and_then(|file| {
+            let mut reader = BufReader::new(file);
+            let (url, item_type, fetched, _) = parse_header(&mut reader)?;
+            let mut body = Vec::new();
+            reader.read_to_end(&mut body)?;
+            Ok(Entry {
+                url,
+                item_type,
+                body,
+                fetched,
+            })
+        });
+        match result {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                warn!("Could not read cache file {:?}: {}", path, err);
+                None
+            }
+        }
+    }
+}
+
+/// Reads the url, item type and fetch time, returning them with the length of the header.
+fn parse_header(reader: &mut impl BufRead) -> io::Result<(Url, ItemType, SystemTime, usize)> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let mut lines = Vec::new();
+    let mut len = 0;
+    for _ in 0..3 {
+        let mut line = String::new();
+        len += reader.read_line(&mut line)?;
+        lines.push(line.trim_end().to_owned());
+    }
+
+    let url = Url::parse(&lines[0]).map_err(|_| invalid("bad url"))?;
+    let item_type = match lines[1].as_bytes() {
+        [b] => ItemType::decode(*b),
+        _ => return Err(invalid("bad item type")),
+    };
+    let secs = lines[2]
+        .parse::<u64>()
+        .map_err(|_| invalid("bad timestamp"))?;
+    Ok((url, item_type, UNIX_EPOCH + Duration::from_secs(secs), len))
+}
+
+fn read_header(path: &Path) -> io::Result<(Url, ItemType, SystemTime, usize)> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len() as usize;
+    let mut reader = BufReader::new(file);
+    let (url, item_type, fetched, header_len) = parse_header(&mut reader)?;
+    Ok((url, item_type, fetched, len.saturating_sub(header_len)))
+}
+
+/// FNV-1a, used for cache file names since it is stable across builds.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for b in bytes {
+        hash ^= u64::from(*b);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(url: &str) -> String {
+        normalize(&Url::parse(url).unwrap(), 70)
+    }
+
+    #[test]
+    fn normalizes_equivalent_urls() {
+        assert_eq!(key("gopher://Example.org"), "gopher://example.org:70/1/");
+        assert_eq!(
+            key("gopher://example.org:70/1/"),
+            "gopher://example.org:70/1/"
+        );
+        assert_eq!(
+            key("gopher://example.org/0/a.txt"),
+            key("gopher://EXAMPLE.org:70/0//a.txt")
+        );
+        assert_ne!(
+            key("gopher://example.org/0/a.txt"),
+            key("gopher://example.org:7070/0/a.txt")
+        );
+        assert_ne!(
+            key("gopher://example.org/0/a.txt"),
+            key("gopher://example.org/1/a.txt")
+        );
+    }
+
+    #[test]
+    fn hits_are_given_the_url_that_was_asked_for() {
+        let mut cache = Cache::new(DEFAULT_CAPACITY, None, 0, 70);
+        let stored = Url::parse("gopher://example.org:70/1/").unwrap();
+        cache.insert(
+            stored,
+            ItemType::Dir,
+            b"iHello\tfake\t(NULL)\t0\r\n".to_vec(),
+        );
+
+        let asked = Url::parse("gopher://Example.org").unwrap();
+        let entry = cache.get(&asked).unwrap();
+        let response = entry
+            .to_response(asked.clone(), ItemType::Dir, &[])
+            .unwrap();
+        assert_eq!(response.url, asked);
+    }
+
+    #[test]
+    fn uses_the_configured_default_port() {
+        let url = Url::parse("gopher://example.org/1/").unwrap();
+        let explicit = Url::parse("gopher://example.org:7070/1/").unwrap();
+        assert_eq!(normalize(&url, 7070), normalize(&explicit, 7070));
+        assert_ne!(normalize(&url, 70), normalize(&explicit, 70));
+    }
+
+    #[test]
+    fn removes_the_oldest_files_past_the_disk_limit() {
+        let dir = std::env::temp_dir().join(format!("gopher-cache-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let url = |n| Url::parse(&format!("gopher://example.org/0/{}", n)).unwrap();
+        let mut cache = Cache::new(DEFAULT_CAPACITY, Some(dir.clone()), 2500, 70);
+        for n in 0..3 {
+            cache.insert(url(n), ItemType::File, vec![b'x'; 1000]);
+            // modification times tell which file is oldest
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let on_disk = Cache::new(DEFAULT_CAPACITY, Some(dir.clone()), 2500, 70);
+        assert!(!on_disk.contains(&url(0)));
+        assert!(on_disk.contains(&url(1)));
+        assert!(on_disk.contains(&url(2)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_queries_and_other_schemes() {
+        assert_eq!(
+            key("gopher://example.org/7/search?cats"),
+            "gopher://example.org:70/7/search?cats"
+        );
+        assert_eq!(key("about:bookmarks"), "about:bookmarks");
+    }
+}
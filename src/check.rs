@@ -0,0 +1,489 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use encoding_rs::Encoding;
+use futures::future::{self, Either};
+use futures::{stream, Future, Stream};
+use gopher::gophermap::{self, Line};
+use gopher::{decode_text, ItemType, Request};
+use serde::Serialize;
+use tokio::runtime::Runtime;
+use url::Url;
+
+use crate::args::{CheckArgs, Format};
+use crate::config::Config;
+use crate::errors::Error;
+use crate::fetch;
+use crate::inspector::{self, Exchange};
+use crate::local;
+
+pub const EXIT_CODES: &str = "EXIT CODES:
+    0    no broken links were found
+    1    any other error
+    2    the URL is not a valid gopher URL
+    3    the server couldn't be reached or closed the connection
+    4    the server took too long to send the menu
+    5    the menu couldn't be read
+    6    the output couldn't be written
+    7    some links are broken, timed out or lead to the wrong type of item";
+
+/// How many links on the same server are checked at once, unless told otherwise.
+pub const DEFAULT_PER_HOST: usize = 4;
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// How many links on the same server to check at once.
+    pub per_host: usize,
+    /// Time after which a link is reported as timed out.
+    pub timeout: Duration,
+    pub default_port: u16,
+    pub encodings: Vec<&'static Encoding>,
+}
+
+impl Options {
+    pub fn new(config: &Config) -> Self {
+        Options {
+            per_host: DEFAULT_PER_HOST,
+            timeout: config.timeout(),
+            default_port: config.default_port,
+            encodings: config.encodings(),
+        }
+    }
+}
+
+/// How a link on a menu turned out.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    /// The server couldn't be reached, sent nothing or sent an error.
+    Broken {
+        reason: String,
+    },
+    Timeout,
+    /// The item isn't what the link says it is, like a menu link that leads to text.
+    WrongType {
+        reason: String,
+    },
+    /// A `URL:` link to another protocol, which isn't followed.
+    Redirect {
+        target: String,
+    },
+    /// A link that needs someone at the keyboard, like a search or a telnet session.
+    Skipped {
+        reason: String,
+    },
+}
+
+impl Status {
+    /// Whether the link needs fixing.
+    pub fn is_problem(&self) -> bool {
+        match self {
+            Status::Broken { .. } | Status::Timeout | Status::WrongType { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Broken { .. } => "broken",
+            Status::Timeout => "timeout",
+            Status::WrongType { .. } => "wrong type",
+            Status::Redirect { .. } => "redirect",
+            Status::Skipped { .. } => "skipped",
+        }
+    }
+
+    /// What went wrong, or where a redirect goes.
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            Status::Broken { reason }
+            | Status::WrongType { reason }
+            | Status::Skipped { reason } => Some(reason.clone()),
+            Status::Redirect { target } => Some(format!("to {}", target)),
+            Status::Timeout => Some("took too long to respond".to_owned()),
+            Status::Ok => None,
+        }
+    }
+}
+
+/// A link on a menu and how it turned out.
+#[derive(Clone, Debug, Serialize)]
+pub struct LinkReport {
+    #[serde(rename = "type")]
+    pub item_type: char,
+    pub display: String,
+    pub url: String,
+    #[serde(flatten)]
+    pub status: Status,
+}
+
+/// Runs `gopher-browser check`, returning the exit code.
+pub fn run(args: &CheckArgs, config: &Config) -> i32 {
+    let options = Options {
+        per_host: args.per_host,
+        ..Options::new(config)
+    };
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let reports = match runtime.block_on(check_url(args.url.clone(), options, |_| ())) {
+        Ok(reports) => reports,
+        Err(err) => return fetch::fail(err),
+    };
+    eprintln!("{}", summary(&reports));
+
+    let shown = reports
+        .iter()
+        .filter(|report| match report.status {
+            Status::Ok | Status::Skipped { .. } => args.all,
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let written = match args.format {
+        Format::Json => serde_json::to_writer(&mut stdout, &shown)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(stdout)),
+        _ => shown
+            .iter()
+            .map(|report| writeln!(stdout, "{}", format_report(report)))
+            .collect(),
+    };
+    if let Err(err) = written.and_then(|_| stdout.flush()) {
+        eprintln!("Could not write output: {}", err);
+        return 6;
+    }
+
+    if reports.iter().any(|report| report.status.is_problem()) {
+        7
+    } else {
+        0
+    }
+}
+
+/// A line of text describing a link report.
+pub fn format_report(report: &LinkReport) -> String {
+    let mut line = format!(
+        "{:<10} {}  {:?}",
+        report.status.name(),
+        report.url,
+        report.display
+    );
+    if let Some(detail) = report.status.detail() {
+        line.push_str(": ");
+        line.push_str(&detail);
+    }
+    line
+}
+
+/// How many links had each kind of problem.
+pub fn summary(reports: &[LinkReport]) -> String {
+    let count = |name: &str| {
+        reports
+            .iter()
+            .filter(|report| report.status.name() == name)
+            .count()
+    };
+    format!(
+        "Checked {} links: {} broken, {} timed out, {} of the wrong type, {} redirects, {} skipped",
+        reports.len(),
+        count("broken"),
+        count("timeout"),
+        count("wrong type"),
+        count("redirect"),
+        count("skipped")
+    )
+}
+
+/// Fetches the menu at `url` and checks its links. `file://` URLs of local gophermaps work too.
+///
+/// The lines of the menu are read as they are, so that `URL:` links and lines without a host
+/// are reported as well. `inspect` is called with every request made over the network.
+pub fn check_url<I>(
+    url: Url,
+    options: Options,
+    inspect: I,
+) -> impl Future<Item = Vec<LinkReport>, Error = Error> + Send
+where
+    I: Fn(Exchange) + Clone + Send + 'static,
+{
+    let timeout = options.timeout;
+    let per_host = options.per_host;
+    let encodings = options.encodings;
+    if url.scheme() == "file" {
+        let map_path = match local::gophermap_path(&url) {
+            Some(map_path) => map_path,
+            None => return Either::A(future::err(Error::NotAMenu(url))),
+        };
+        let text = match fs::read(&map_path) {
+            Ok(buf) => decode_text(&buf, &encodings),
+            Err(err) => return Either::A(future::err(err.into())),
+        };
+        let dir = map_path
+            .parent()
+            .unwrap_or_else(|| Path::new("/"))
+            .to_path_buf();
+        let root = local::find_root(&dir);
+        let resolve = move |line: &Line| local::resolve(line, &root, &dir);
+        let lines = gophermap::parse(&text);
+        return Either::B(Either::A(check(lines, resolve, timeout, per_host, inspect)));
+    }
+
+    let request = match Request::with_default_port(url, options.default_port) {
+        Ok(request) => request,
+        Err(err) => return Either::A(future::err(err.into())),
+    };
+    match request.item_type {
+        ItemType::Dir | ItemType::IndexServer => (),
+        _ => return Either::A(future::err(Error::NotAMenu(request.url))),
+    }
+    let checked = inspector::fetch(request, timeout, |_| (), inspect.clone())
+        .from_err()
+        .and_then(move |buf| {
+            let lines = gophermap::parse(&decode_text(&buf, &encodings));
+            check(lines, Line::url, timeout, per_host, inspect)
+        });
+    Either::B(Either::B(checked))
+}
+
+/// Checks every link in a menu, at most `per_host` at a time on each server.
+///
+/// `resolve` gives the URL of a link, which may depend on where the menu came from. The reports
+/// are in the order of the links on the menu.
+pub fn check<F, I>(
+    lines: Vec<Line>,
+    resolve: F,
+    timeout: Duration,
+    per_host: usize,
+    inspect: I,
+) -> impl Future<Item = Vec<LinkReport>, Error = Error> + Send
+where
+    F: Fn(&Line) -> Option<Url>,
+    I: Fn(Exchange) + Clone + Send + 'static,
+{
+    let mut reports = Vec::new();
+    let mut hosts = BTreeMap::<(String, u16), Vec<(usize, Request)>>::new();
+    for line in lines {
+        if line.item_type == ItemType::Other(b'i') {
+            continue;
+        }
+
+        let url = resolve(&line);
+        let (target, status) = if line.selector.starts_with("URL:") {
+            let target = line.selector["URL:".len()..].to_owned();
+            (target.clone(), Status::Redirect { target })
+        } else if let Some(url) = url {
+            let status = match link_request(line.item_type, &url) {
+                Ok(request) => {
+                    let key = (request.host.to_lowercase(), request.port);
+                    hosts.entry(key).or_default().push((reports.len(), request));
+                    Status::Ok
+                }
+                Err(status) => status,
+            };
+            (url.to_string(), status)
+        } else {
+            let reason = "has no host, or a selector that can't be made into a URL";
+            (
+                line.selector.clone(),
+                Status::Broken {
+                    reason: reason.to_owned(),
+                },
+            )
+        };
+        reports.push(LinkReport {
+            item_type: line.item_type.encode() as char,
+            display: line.display,
+            url: target,
+            status,
+        });
+    }
+
+    // servers are checked side by side, links on the same server a few at a time
+    let checks = hosts
+        .into_iter()
+        .map(|(_, requests)| {
+            let inspect = inspect.clone();
+            stream::iter_ok::<_, Error>(requests)
+                .map(move |(n, request)| {
+                    let item_type = request.item_type;
+                    inspector::fetch(request, timeout, |_| (), inspect.clone())
+                        .then(move |result| Ok((n, status(item_type, result))))
+                })
+                .buffer_unordered(per_host.max(1))
+                .collect()
+        })
+        .collect::<Vec<_>>();
+    future::join_all(checks).map(move |results| {
+        for (n, status) in results.into_iter().flatten() {
+            reports[n].status = status;
+        }
+        reports
+    })
+}
+
+/// The request to check a link with, or how it turned out if it isn't fetched.
+fn link_request(item_type: ItemType, url: &Url) -> Result<Request, Status> {
+    match url.scheme() {
+        "gopher" => (),
+        // links in local gophermaps
+        "file" => {
+            return Err(match url.to_file_path() {
+                Ok(ref path) if path.exists() => Status::Ok,
+                _ => Status::Broken {
+                    reason: "no such file".to_owned(),
+                },
+            })
+        }
+        _ => {
+            return Err(Status::Redirect {
+                target: url.to_string(),
+            })
+        }
+    }
+
+    let skipped = match item_type {
+        ItemType::IndexServer => Some("searches need a query"),
+        ItemType::Telnet | ItemType::Tn3270 => Some("telnet sessions aren't checked"),
+        ItemType::CsoServer => Some("phone books aren't checked"),
+        ItemType::Error => Some("error lines don't link anywhere"),
+        _ => None,
+    };
+    if let Some(reason) = skipped {
+        return Err(Status::Skipped {
+            reason: reason.to_owned(),
+        });
+    }
+    Request::from_url(url.clone()).map_err(|err| Status::Broken {
+        reason: err.to_string(),
+    })
+}
+
+/// How a link turned out, judging by what the server sent back.
+fn status(item_type: ItemType, result: Result<Vec<u8>, gopher::Error>) -> Status {
+    let buf = match result {
+        Ok(buf) => buf,
+        Err(gopher::Error::Timeout(_)) => return Status::Timeout,
+        Err(err) => {
+            return Status::Broken {
+                reason: err.to_string(),
+            }
+        }
+    };
+    if buf.is_empty() {
+        return Status::Broken {
+            reason: "the server sent nothing back".to_owned(),
+        };
+    }
+
+    let text = String::from_utf8_lossy(&buf);
+    let lines = text
+        .lines()
+        .filter(|line| !line.is_empty() && *line != ".")
+        .collect::<Vec<_>>();
+    let menu_lines = lines
+        .iter()
+        .filter(|line| line.split('\t').count() >= 4)
+        .count();
+    let is_binary = buf.contains(&0);
+
+    // servers answer selectors they don't have with a menu holding an error line
+    if let Some(first) = lines.first() {
+        if first.starts_with('3') && first.split('\t').count() >= 4 {
+            return Status::Broken {
+                reason: format!("the server says {:?}", Line::parse(first).display),
+            };
+        }
+    }
+
+    let reason = match item_type {
+        ItemType::Dir if menu_lines == 0 && is_binary => {
+            Some("links to a menu, but the server sent binary data")
+        }
+        ItemType::Dir if menu_lines == 0 => Some("links to a menu, but the server sent text"),
+        ItemType::File if is_binary => Some("links to text, but the server sent binary data"),
+        ItemType::File if !lines.is_empty() && menu_lines == lines.len() => {
+            Some("links to text, but the server sent a menu")
+        }
+        ItemType::Gif if !buf.starts_with(b"GIF8") => {
+            Some("links to a GIF, but the server sent something else")
+        }
+        _ => None,
+    };
+    match reason {
+        Some(reason) => Status::WrongType {
+            reason: reason.to_owned(),
+        },
+        None => Status::Ok,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_of(item_type: ItemType, body: &[u8]) -> Status {
+        status(item_type, Ok(body.to_vec()))
+    }
+
+    #[test]
+    fn text_where_a_menu_was_expected() {
+        assert_eq!(
+            status_of(ItemType::Dir, b"Just some notes.\r\n").name(),
+            "wrong type"
+        );
+        let menu = b"1Phlog\t/phlog\texample.com\t70\r\n.\r\n";
+        assert_eq!(status_of(ItemType::Dir, menu), Status::Ok);
+    }
+
+    #[test]
+    fn a_menu_where_text_was_expected() {
+        let menu = b"1Phlog\t/phlog\texample.com\t70\r\n0About\t/about.txt\texample.com\t70\r\n";
+        assert_eq!(
+            status_of(ItemType::File, menu),
+            Status::WrongType {
+                reason: "links to text, but the server sent a menu".to_owned()
+            }
+        );
+        assert_eq!(status_of(ItemType::File, b"Hello\tthere\n"), Status::Ok);
+    }
+
+    #[test]
+    fn error_lines_are_broken() {
+        let menu = b"3'/missing' doesn't exist!\t\terror.host\t1\r\n.\r\n";
+        assert_eq!(
+            status_of(ItemType::File, menu),
+            Status::Broken {
+                reason: "the server says \"'/missing' doesn't exist!\"".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn empty_bodies_are_broken() {
+        assert_eq!(status_of(ItemType::Binary, b"").name(), "broken");
+    }
+
+    #[test]
+    fn gifs_are_checked_by_their_header() {
+        assert_eq!(
+            status_of(ItemType::Gif, b"GIF89a\x01\x00\x01\x00"),
+            Status::Ok
+        );
+        assert_eq!(
+            status_of(ItemType::Gif, b"\x89PNG\r\n\x1a\n").name(),
+            "wrong type"
+        );
+    }
+
+    #[test]
+    fn timeouts() {
+        let url = Url::parse("gopher://example.com/1/").unwrap();
+        let result = Err(gopher::Error::Timeout(url));
+        assert_eq!(status(ItemType::Dir, result), Status::Timeout);
+    }
+}
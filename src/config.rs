@@ -0,0 +1,428 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+use toml::Value;
+use url::Url;
+
+use crate::errors::Error;
+
+/// Settings read from `$XDG_CONFIG_HOME/gopher-browser/config.toml`.
+///
+/// Every key is optional; missing keys take their default value.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    pub home_page: String,
+    pub default_port: u16,
+    /// Seconds to wait for a server before giving up.
+    pub timeout: u64,
+    /// Encodings to try, in order, for text that isn't valid in the previous ones.
+    pub encodings: Vec<String>,
+    /// Where binary files are saved; the user's download directory if unset.
+    pub download_dir: Option<PathBuf>,
+    // tables have to come after plain values in toml
+    pub font: Font,
+    pub colors: Colors,
+    pub window: WindowSize,
+    pub cache: CacheConfig,
+    pub keys: Keys,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Font {
+    pub family: String,
+    pub size: u32,
+}
+
+/// Colors for page contents, as `#rrggbb`; unset colors come from the GTK theme.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Colors {
+    pub foreground: Option<String>,
+    pub background: Option<String>,
+    pub link: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowSize {
+    pub width: i32,
+    pub height: i32,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Number of responses kept in memory.
+    pub capacity: usize,
+    /// Whether responses are also kept on disk, which offline mode relies on.
+    pub disk: bool,
+    /// Megabytes of responses kept on disk; the oldest are removed past this.
+    pub disk_limit_mib: u64,
+}
+
+/// Keys for getting around a page without the mouse, as GTK accelerator names like "j", "Down"
+/// or "<Control>n". Typing a number and then a `follow_link` key opens that link.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Keys {
+    pub next_link: Vec<String>,
+    pub previous_link: Vec<String>,
+    pub follow_link: Vec<String>,
+    /// Goes to the menu one selector level up.
+    pub up: Vec<String>,
+    pub find: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            home_page: "gopher://iptq.io".to_owned(),
+            default_port: 70,
+            timeout: 30,
+            encodings: vec!["utf-8".to_owned(), "iso-8859-1".to_owned()],
+            download_dir: None,
+            font: Font::default(),
+            colors: Colors::default(),
+            window: WindowSize::default(),
+            cache: CacheConfig::default(),
+            keys: Keys::default(),
+        }
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Font {
+            family: "Monospace".to_owned(),
+            size: 11,
+        }
+    }
+}
+
+impl Default for WindowSize {
+    fn default() -> Self {
+        WindowSize {
+            width: 854,
+            height: 480,
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            capacity: crate::cache::DEFAULT_CAPACITY,
+            disk: true,
+            disk_limit_mib: crate::cache::DEFAULT_DISK_LIMIT_MIB,
+        }
+    }
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        let keys = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Keys {
+            next_link: keys(&["j", "Down"]),
+            previous_link: keys(&["k", "Up"]),
+            follow_link: keys(&["Return", "KP_Enter"]),
+            up: keys(&["u"]),
+            find: keys(&["slash"]),
+        }
+    }
+}
+
+impl Keys {
+    /// Every binding with the name of its key in the config file.
+    pub fn bindings(&self) -> Vec<(&'static str, &[String])> {
+        vec![
+            ("keys.next_link", &self.next_link),
+            ("keys.previous_link", &self.previous_link),
+            ("keys.follow_link", &self.follow_link),
+            ("keys.up", &self.up),
+            ("keys.find", &self.find),
+        ]
+    }
+}
+
+impl Config {
+    /// Reads and validates the config file; a missing file gives the defaults.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let context = |msg: String| Error::Config(format!("{}: {}", path.display(), msg));
+        let text = fs::read_to_string(path).map_err(|err| context(err.to_string()))?;
+        let config: Config = toml::from_str(&text).map_err(|err| context(err.to_string()))?;
+        config.validate().map_err(context)?;
+        Ok(config)
+    }
+
+    /// Writes the settings that differ from the defaults, and the ones the file already had, so
+    /// that settings the user never touched keep following the defaults.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        self.validate().map_err(Error::Config)?;
+        let to_value =
+            |config: &Config| Value::try_from(config).map_err(|err| Error::Config(err.to_string()));
+        let existing = fs::read_to_string(path)
+            .ok()
+            .and_then(|text| text.parse::<Value>().ok());
+        let mut value = to_value(self)?;
+        remove_defaults(
+            &mut value,
+            &to_value(&Config::default())?,
+            existing.as_ref(),
+        );
+        let text = toml::to_string_pretty(&value).map_err(|err| Error::Config(err.to_string()))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Checks the values, listing every problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        match Url::parse(&self.home_page) {
+            Ok(ref url) if ["gopher", "file", "about"].contains(&url.scheme()) => (),
+            Ok(_) => problems.push(format!(
+                "home_page: {:?} is not a gopher, file or about URL",
+                self.home_page
+            )),
+            Err(err) => problems.push(format!("home_page: {:?} is {}", self.home_page, err)),
+        }
+        if self.default_port == 0 {
+            problems.push("default_port: must be between 1 and 65535".to_owned());
+        }
+        if self.timeout == 0 {
+            problems.push("timeout: must be at least 1 second".to_owned());
+        }
+        if self.encodings.is_empty() {
+            problems.push("encodings: at least one encoding is needed".to_owned());
+        }
+        for label in &self.encodings {
+            if Encoding::for_label(label.as_bytes()).is_none() {
+                problems.push(format!("encodings: unknown encoding {:?}", label));
+            }
+        }
+        if let Some(dir) = &self.download_dir {
+            if dir.exists() && !dir.is_dir() {
+                problems.push(format!(
+                    "download_dir: {} is not a directory",
+                    dir.display()
+                ));
+            }
+        }
+        if self.font.family.trim().is_empty() {
+            problems.push("font.family: must not be empty".to_owned());
+        }
+        if self.font.size < 4 || self.font.size > 96 {
+            problems.push(format!(
+                "font.size: {} is not between 4 and 96",
+                self.font.size
+            ));
+        }
+        let colors = [
+            ("colors.foreground", &self.colors.foreground),
+            ("colors.background", &self.colors.background),
+            ("colors.link", &self.colors.link),
+        ];
+        for (key, color) in colors.iter() {
+            if let Some(color) = color {
+                if !is_hex_color(color) {
+                    problems.push(format!("{}: {:?} is not a #rrggbb color", key, color));
+                }
+            }
+        }
+        if self.window.width < 200 || self.window.height < 150 {
+            problems.push(format!(
+                "window: {}x{} is smaller than 200x150",
+                self.window.width, self.window.height
+            ));
+        }
+        if self.cache.capacity == 0 {
+            problems.push("cache.capacity: must be at least 1".to_owned());
+        }
+        if self.cache.disk_limit_mib == 0 {
+            problems.push("cache.disk_limit_mib: must be at least 1".to_owned());
+        }
+        // whether GTK knows the names is checked when the window starts
+        for (key, names) in self.keys.bindings() {
+            if names.iter().any(|name| name.trim().is_empty()) {
+                problems.push(format!("{}: key names must not be empty", key));
+            }
+            if names
+                .iter()
+                .any(|name| name.len() == 1 && name.as_bytes()[0].is_ascii_digit())
+            {
+                problems.push(format!("{}: numbers are used to pick links", key));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("\n"))
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout)
+    }
+
+    /// The configured encodings; unknown labels are skipped.
+    pub fn encodings(&self) -> Vec<&'static Encoding> {
+        self.encodings
+            .iter()
+            .filter_map(|label| Encoding::for_label(label.as_bytes()))
+            .collect()
+    }
+
+    /// The directory binary files are saved to.
+    pub fn download_dir(&self) -> PathBuf {
+        self.download_dir
+            .clone()
+            .or_else(dirs::download_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// CSS for page contents with the configured font and colors.
+    pub fn css(&self) -> String {
+        let mut css = format!(
+            "textview, button.link {{ font-family: \"{}\"; font-size: {}pt; }}\n",
+            self.font.family.replace('"', ""),
+            self.font.size
+        );
+        if let Some(color) = &self.colors.foreground {
+            css.push_str(&format!("textview text {{ color: {}; }}\n", color));
+        }
+        if let Some(color) = &self.colors.background {
+            css.push_str(&format!(
+                "textview text, scrolledwindow viewport {{ background-color: {}; }}\n",
+                color
+            ));
+        }
+        if let Some(color) = &self.colors.link {
+            css.push_str(&format!("*:link {{ color: {}; }}\n", color));
+        }
+        css
+    }
+}
+
+/// Takes the settings that are the same as in `default` out of `value`, unless `existing` has
+/// them. Tables left empty are taken out too.
+fn remove_defaults(value: &mut Value, default: &Value, existing: Option<&Value>) {
+    let table = match value.as_table_mut() {
+        Some(table) => table,
+        None => return,
+    };
+    let keys = table.keys().cloned().collect::<Vec<_>>();
+    for key in keys {
+        let existing = existing.and_then(|existing| existing.get(&key));
+        let default = match default.get(&key) {
+            Some(default) => default,
+            None => continue,
+        };
+        let is_default = match table.get_mut(&key) {
+            Some(value @ Value::Table(_)) => {
+                remove_defaults(value, default, existing);
+                value.as_table().map_or(false, |table| table.is_empty())
+            }
+            Some(value) => value == default,
+            None => false,
+        };
+        if is_default && existing.is_none() {
+            table.remove(&key);
+        }
+    }
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("gopher-config-test-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn missing_files_give_the_defaults() {
+        let config = Config::load(&temp_file("missing")).unwrap();
+        assert_eq!(config.home_page, "gopher://iptq.io");
+        assert_eq!(config.download_dir, None);
+        assert_eq!(
+            config.cache.disk_limit_mib,
+            crate::cache::DEFAULT_DISK_LIMIT_MIB
+        );
+    }
+
+    #[test]
+    fn missing_keys_take_their_defaults() {
+        let path = temp_file("partial");
+        fs::write(&path, "timeout = 5\n\n[font]\nsize = 14\n").unwrap();
+        let config = Config::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.timeout, 5);
+        assert_eq!(config.font.size, 14);
+        assert_eq!(config.font.family, "Monospace");
+        assert_eq!(config.default_port, 70);
+    }
+
+    #[test]
+    fn invalid_files_say_what_is_wrong() {
+        let path = temp_file("invalid");
+        fs::write(&path, "timeout = 0\n").unwrap();
+        let err = Config::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(err
+            .to_string()
+            .contains("timeout: must be at least 1 second"));
+    }
+
+    #[test]
+    fn saves_only_what_was_changed() {
+        let path = temp_file("changed");
+        let mut config = Config::default();
+        config.timeout = 10;
+        config.font.size = 14;
+        config.colors.link = Some("#0044aa".to_owned());
+        config.save(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let loaded = Config::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.timeout, 10);
+        assert_eq!(loaded.font.size, 14);
+        assert_eq!(loaded.colors.link.as_deref(), Some("#0044aa"));
+        for key in &["home_page", "download_dir", "family", "[window]", "[keys]"] {
+            assert!(!text.contains(key), "{} was saved:\n{}", key, text);
+        }
+    }
+
+    #[test]
+    fn keeps_settings_the_file_had() {
+        let path = temp_file("kept");
+        fs::write(&path, "home_page = \"gopher://iptq.io\"\n").unwrap();
+        let mut config = Config::load(&path).unwrap();
+        config.default_port = 7070;
+        config.save(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let saved = text.parse::<Value>().unwrap();
+        let keys = saved.as_table().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, vec!["default_port", "home_page"]);
+        assert_eq!(saved["default_port"].as_integer(), Some(7070));
+    }
+}
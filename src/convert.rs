@@ -0,0 +1,73 @@
+//! Converting responses to other formats, for "Save page as" and `gopher-browser fetch`.
+
+use std::path::Path;
+
+use gopher::Response;
+
+use crate::args::Format;
+use crate::render::{
+    AnsiRenderer, GemtextRenderer, HtmlRenderer, MarkdownRenderer, Renderer, TextRenderer,
+};
+
+/// The formats a page can be saved in from the browser, as their names and the text to show
+/// for them.
+pub const SAVE_FORMATS: &[(&str, &str)] = &[
+    ("text", "Plain text"),
+    ("gophermap", "Gophermap"),
+    ("html", "HTML page"),
+    ("markdown", "Markdown"),
+    ("gemtext", "Gemtext"),
+];
+
+/// The response in `format`, ready to be written to a file.
+///
+/// Gophermaps are the menu as a server would send it, so binary and text files stay as they are.
+pub fn convert(response: &Response, format: Format) -> Vec<u8> {
+    match format {
+        Format::Text => TextRenderer.render(response).into_bytes(),
+        Format::Json => {
+            let mut json = serde_json::to_vec(response).expect("failed to serialize response");
+            json.push(b'\n');
+            json
+        }
+        Format::Html => HtmlRenderer.render(response).into_bytes(),
+        Format::Ansi => AnsiRenderer.render(response).into_bytes(),
+        Format::Gophermap => response.to_bytes(),
+        Format::Markdown => MarkdownRenderer.render(response).into_bytes(),
+        Format::Gemtext => GemtextRenderer.render(response).into_bytes(),
+    }
+}
+
+/// A name for a file saved in `format`, from the name of what it was made from.
+pub fn file_name(name: &str, format: Format) -> String {
+    let stem = Path::new(name)
+        .file_stem()
+        .map_or(name.into(), |stem| stem.to_string_lossy());
+    let extension = match format {
+        Format::Text | Format::Ansi => "txt",
+        Format::Json => "json",
+        Format::Html => "html",
+        // menus are read from files with this name, whatever the directory is called
+        Format::Gophermap => return "gophermap".to_owned(),
+        Format::Markdown => "md",
+        Format::Gemtext => "gmi",
+    };
+    format!("{}.{}", stem, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_get_the_extension_of_the_format() {
+        assert_eq!(file_name("notes.txt", Format::Markdown), "notes.md");
+        assert_eq!(file_name("phlog", Format::Html), "phlog.html");
+        assert_eq!(
+            file_name("archive.tar.gz", Format::Gemtext),
+            "archive.tar.gmi"
+        );
+        assert_eq!(file_name("notes.txt", Format::Ansi), "notes.txt");
+        assert_eq!(file_name("phlog", Format::Gophermap), "gophermap");
+    }
+}
@@ -5,6 +5,8 @@ use std::string::FromUtf8Error;
 use std::sync::mpsc::SendError as StdSendError;
 
 use futures::sync::mpsc::SendError;
+use gopher::ItemType;
+use url::Url;
 
 use crate::events::Reply;
 
@@ -15,6 +17,14 @@ pub enum Error {
     SendReply(SendError<Reply>),
     String(FromUtf8Error),
     IO(io::Error),
+    InvalidUrl(String),
+    Offline(Url),
+    Timeout(Url),
+    UnsupportedType(ItemType),
+    NotAMenu(Url),
+    /// There's no copy of the bytes of a page to show or save.
+    NoSource(Url),
+    Config(String),
 }
 
 impl From<io::Error> for Error {
@@ -23,6 +33,17 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<gopher::Error> for Error {
+    fn from(err: gopher::Error) -> Self {
+        match err {
+            gopher::Error::IO(err) => Error::IO(err),
+            gopher::Error::InvalidUrl(url) => Error::InvalidUrl(url),
+            gopher::Error::Timeout(url) => Error::Timeout(url),
+            gopher::Error::UnsupportedType(item_type) => Error::UnsupportedType(item_type),
+        }
+    }
+}
+
 impl From<StdSendError<Reply>> for Error {
     fn from(err: StdSendError<Reply>) -> Self {
         Error::StdSendReply(err)
@@ -44,8 +65,22 @@ impl From<FromUtf8Error> for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            ChannelRecv => write!(f, "ChannelRecv"),
+            Error::ChannelRecv => write!(f, "ChannelRecv"),
+            Error::StdSendReply(err) => write!(f, "Error sending reply: {}", err),
+            Error::SendReply(err) => write!(f, "Error sending reply: {}", err),
+            Error::String(err) => write!(f, "Invalid UTF-8: {}", err),
             Error::IO(err) => write!(f, "IO error: {}", err),
+            Error::InvalidUrl(url) => write!(f, "Invalid URL: {}", url),
+            Error::Offline(url) => write!(f, "{} is not available offline", url),
+            Error::Timeout(url) => write!(f, "{} took too long to respond", url),
+            Error::UnsupportedType(item_type) => write!(
+                f,
+                "Items of type '{}' aren't supported",
+                item_type.encode() as char
+            ),
+            Error::NotAMenu(url) => write!(f, "{} is not a menu", url),
+            Error::NoSource(url) => write!(f, "There is no copy of the source of {}", url),
+            Error::Config(msg) => write!(f, "Invalid configuration: {}", msg),
         }
     }
 }
@@ -1,16 +1,51 @@
+use std::sync::mpsc::Sender as StdSender;
 use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
+use gopher::{Request, Response};
 use relm::Sender as RelmSender;
+use url::Url;
 
-use crate::gopher_async::{Request, Response};
+use crate::check::{LinkReport, Options as CheckOptions};
+use crate::errors::Error;
+use crate::inspector::Exchange;
+use crate::mirror::{Options as MirrorOptions, Progress as MirrorProgress};
 
-type Sender = Arc<Mutex<RelmSender<Reply>>>;
+/// Where the replies to an event go, depending on the front-end that sent it.
+#[derive(Clone)]
+pub enum Sender {
+    Window(Arc<Mutex<RelmSender<Reply>>>),
+    Terminal(StdSender<Reply>),
+}
+
+impl Sender {
+    pub fn send(&self, reply: Reply) -> Result<(), Error> {
+        match self {
+            Sender::Window(sender) => sender.lock().unwrap().send(reply).map_err(Error::from),
+            Sender::Terminal(sender) => sender.send(reply).map_err(Error::from),
+        }
+    }
+}
 
 pub enum Event {
     MakeRequest(Request, Sender),
+    Mirror(Url, MirrorOptions, Sender),
+    /// Checks the links on the menu at a URL.
+    CheckLinks(Url, CheckOptions, Sender),
 }
 
 #[derive(Debug)]
 pub enum Reply {
-    Response(Response),
+    /// A response, with the time it was fetched if it was served from the cache.
+    Response(Response, Option<SystemTime>),
+    /// Bytes of the response to a request received so far.
+    Progress(Url, usize),
+    /// How a request for a page went, whether it succeeded or not, or that the cache answered it.
+    Exchange(Box<Exchange>),
+    MirrorProgress(MirrorProgress),
+    /// How the links on the menu at a URL turned out.
+    LinkReports(Url, Vec<LinkReport>),
+    /// URLs from another invocation of the browser; the home page if empty.
+    OpenUrls(Vec<Url>),
+    Fail(Box<Error>),
 }
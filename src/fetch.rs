@@ -0,0 +1,83 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use gopher::{Client, Request, Response, ResponseKind};
+use tokio::runtime::Runtime;
+
+use crate::args::{FetchArgs, Format};
+use crate::config::Config;
+use crate::convert;
+use crate::errors::Error;
+
+pub const EXIT_CODES: &str = "EXIT CODES:
+    0    the resource was printed or saved
+    1    any other error
+    2    the URL is not a valid gopher URL
+    3    the server couldn't be reached or closed the connection
+    4    the server took too long to respond
+    5    the response couldn't be read
+    6    the output couldn't be written";
+
+/// Runs `gopher-browser fetch`, returning the exit code.
+///
+/// Menus are printed as text with the URL of every link, text files as they are, and binary
+/// files are saved to a file. With `--format json`, the response is printed as a JSON object
+/// instead, with a `lines` array for menus, and the other formats are converted by `convert`.
+pub fn run(args: &FetchArgs, config: &Config) -> i32 {
+    let request = match Request::with_default_port(args.url.clone(), config.default_port) {
+        Ok(request) => request,
+        Err(err) => return fail(err),
+    };
+    let item_type = request.item_type;
+    let output = args.output.clone().unwrap_or_else(|| {
+        PathBuf::from(request.file_name().unwrap_or_else(|| "download".to_owned()))
+    });
+
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    let buf = match runtime.block_on(Client::fetch_timeout(request, config.timeout())) {
+        Ok(buf) => buf,
+        Err(err) => return fail(err),
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let written = if args.raw {
+        stdout.write_all(&buf)
+    } else {
+        let response = match Response::decode(args.url.clone(), item_type, buf, &config.encodings())
+        {
+            Ok(response) => response,
+            Err(err) => return fail(err),
+        };
+        match (&response.kind, args.format) {
+            (ResponseKind::BinaryFile(buf), format) => fs::write(&output, buf).and_then(|_| {
+                eprintln!("Saved {} bytes to {}", buf.len(), output.display());
+                match format {
+                    Format::Json => stdout.write_all(&convert::convert(&response, format)),
+                    _ => Ok(()),
+                }
+            }),
+            (_, format) => stdout.write_all(&convert::convert(&response, format)),
+        }
+    };
+    match written.and_then(|_| stdout.flush()) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Could not write output: {}", err);
+            6
+        }
+    }
+}
+
+pub fn fail(err: impl Into<Error>) -> i32 {
+    let err = err.into();
+    eprintln!("{}", err);
+    match err {
+        Error::InvalidUrl(_) => 2,
+        Error::IO(_) => 3,
+        Error::Timeout(_) => 4,
+        Error::String(_) | Error::UnsupportedType(_) | Error::NotAMenu(_) => 5,
+        _ => 1,
+    }
+}
@@ -0,0 +1,115 @@
+use gopher::ItemType;
+use url::percent_encoding::percent_decode;
+use url::Url;
+
+use crate::render;
+
+/// Which lines of a menu to show.
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    /// Words that must all be in the display string or the selector of a link. Words like
+    /// "type:1" or "type:dir" match the item type instead, by its character or its tag.
+    pub text: String,
+    pub only_dirs: bool,
+    pub only_downloads: bool,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty() && !self.only_dirs && !self.only_downloads
+    }
+
+    /// Whether to show a line of a menu. Information lines are hidden while filtering.
+    pub fn matches(&self, item_type: Option<ItemType>, display: &str, url: Option<&Url>) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        let item_type = match item_type {
+            Some(item_type) => item_type,
+            None => return false,
+        };
+        if self.only_dirs || self.only_downloads {
+            let wanted = (self.only_dirs && item_type == ItemType::Dir)
+                || (self.only_downloads && is_download(item_type));
+            if !wanted {
+                return false;
+            }
+        }
+
+        let display = display.to_lowercase();
+        let selector = url.map(selector).unwrap_or_default().to_lowercase();
+        self.text.split_whitespace().all(|word| {
+            let word = word.to_lowercase();
+            if word.starts_with("type:") {
+                let wanted = &word["type:".len()..];
+                let code = (item_type.encode() as char).to_string();
+                wanted.eq_ignore_ascii_case(&code)
+                    || wanted.eq_ignore_ascii_case(&render::tag(item_type))
+            } else {
+                display.contains(&word) || selector.contains(&word)
+            }
+        })
+    }
+}
+
+/// Whether items of this type are saved to disk rather than shown.
+pub fn is_download(item_type: ItemType) -> bool {
+    match item_type {
+        ItemType::BinHex
+        | ItemType::Dos
+        | ItemType::Uuencoded
+        | ItemType::Binary
+        | ItemType::Gif
+        | ItemType::Image => true,
+        _ => false,
+    }
+}
+
+/// The selector of a gopher URL, or the path of another one.
+fn selector(url: &Url) -> String {
+    let path = if url.scheme() == "gopher" {
+        // the path is the item type followed by the selector (RFC 4266)
+        url.path().get(2..).unwrap_or("")
+    } else {
+        url.path()
+    };
+    percent_decode(path.as_bytes())
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(text: &str) -> Filter {
+        Filter {
+            text: text.to_owned(),
+            ..Filter::default()
+        }
+    }
+
+    #[test]
+    fn matches_words_in_the_display_string_or_selector() {
+        let url = Url::parse("gopher://example.org/0/phlog/Cats.txt").unwrap();
+        let url = Some(&url);
+        assert!(filter("").matches(None, "anything", None));
+        assert!(filter("about CATS").matches(Some(ItemType::File), "All about dogs", url));
+        assert!(!filter("about birds").matches(Some(ItemType::File), "All about dogs", url));
+        assert!(!filter("about").matches(None, "All about dogs", None));
+    }
+
+    #[test]
+    fn matches_item_types() {
+        assert!(filter("type:1").matches(Some(ItemType::Dir), "Phlog", None));
+        assert!(filter("type:DIR").matches(Some(ItemType::Dir), "Phlog", None));
+        assert!(!filter("type:0").matches(Some(ItemType::Dir), "Phlog", None));
+
+        let downloads = Filter {
+            only_downloads: true,
+            ..Filter::default()
+        };
+        assert!(downloads.matches(Some(ItemType::Gif), "Cat", None));
+        assert!(!downloads.matches(Some(ItemType::Dir), "Cats", None));
+    }
+}
@@ -0,0 +1,79 @@
+use regex::{Error, RegexBuilder};
+
+/// What to look for on a page.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    pub text: String,
+    pub match_case: bool,
+    /// Whether `text` is a regular expression rather than plain text.
+    pub regex: bool,
+}
+
+/// Finds every match of `query` in `text`, as the character offsets where it starts and ends.
+///
+/// Empty matches, like those of `^`, are left out since there's nothing to highlight.
+pub fn find(text: &str, query: &Query) -> Result<Vec<(usize, usize)>, Error> {
+    if query.text.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pattern = if query.regex {
+        query.text.clone()
+    } else {
+        regex::escape(&query.text)
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(!query.match_case)
+        .build()?;
+
+    // text views count in characters, regexes in bytes
+    let mut matches = Vec::new();
+    let mut chars = 0;
+    let mut last = 0;
+    for found in regex.find_iter(text).filter(|found| !found.as_str().is_empty()) {
+        chars += text[last..found.start()].chars().count();
+        let start = chars;
+        chars += found.as_str().chars().count();
+        last = found.end();
+        matches.push((start, chars));
+    }
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(text: &str, match_case: bool, regex: bool) -> Query {
+        Query {
+            text: text.to_owned(),
+            match_case,
+            regex,
+        }
+    }
+
+    #[test]
+    fn finds_text_in_characters() {
+        let text = "Café, cafe and CAFE";
+        assert_eq!(
+            find(text, &query("cafe", false, false)).unwrap(),
+            vec![(6, 10), (15, 19)]
+        );
+        assert_eq!(
+            find(text, &query("cafe", true, false)).unwrap(),
+            vec![(6, 10)]
+        );
+        assert_eq!(find(text, &query("", false, false)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn finds_regexes() {
+        let text = "one two\nthree";
+        assert_eq!(
+            find(text, &query(r"t\w+", false, true)).unwrap(),
+            vec![(4, 7), (8, 13)]
+        );
+        assert_eq!(find(text, &query("(?m)^", false, true)).unwrap(), vec![]);
+        assert_eq!(find(text, &query("t.o", false, false)).unwrap(), vec![]);
+        assert!(find(text, &query("(", false, true)).is_err());
+    }
+}
@@ -1,8 +0,0 @@
-mod client;
-mod request;
-mod response;
-mod types;
-
-pub use self::client::Client;
-pub use self::request::{Request, RequestCodec};
-pub use self::response::{Response, ResponseCodec};
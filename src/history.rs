@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local, TimeZone};
+use gopher::{ItemType, MenuEntry, Response, ResponseKind};
+use url::Url;
+
+use crate::bookmarks::default_title;
+use crate::errors::Error;
+
+#[derive(Clone, Debug)]
+pub struct Visit {
+    pub time: DateTime<Local>,
+    pub url: Url,
+    pub item_type: ItemType,
+    pub title: String,
+}
+
+/// Every page that was opened, oldest first.
+///
+/// Visits are appended to a tab separated file as they happen, one per line: unix time, item
+/// type, URL and title. Without a file, as in private mode, they are only kept in memory.
+pub struct History {
+    path: Option<PathBuf>,
+    visits: Vec<Visit>,
+}
+
+impl History {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let visits = match &path {
+            Some(path) if path.exists() => match fs::read_to_string(path) {
+                Ok(text) => text.lines().filter_map(parse_line).collect(),
+                Err(err) => {
+                    error!("Could not read history from {:?}: {}", path, err);
+                    Vec::new()
+                }
+            },
+            _ => Vec::new(),
+        };
+        History { path, visits }
+    }
+
+    /// Records a visit to the page for `response`.
+    pub fn record(&mut self, response: &Response) -> Result<(), Error> {
+        let visit = Visit {
+            time: Local::now(),
+            url: response.url.clone(),
+            item_type: response.item_type,
+            title: title(response),
+        };
+
+        if let Some(path) = &self.path {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}",
+                visit.time.timestamp(),
+                visit.item_type.encode() as char,
+                visit.url,
+                visit.title.replace('\t', " ")
+            )?;
+        }
+        self.visits.push(visit);
+        Ok(())
+    }
+
+    /// Unique URLs, most recently visited first.
+    pub fn urls(&self) -> Vec<&Url> {
+        let mut seen = HashSet::new();
+        self.visits
+            .iter()
+            .rev()
+            .map(|visit| &visit.url)
+            .filter(|url| seen.insert(*url))
+            .collect()
+    }
+
+    /// The menu shown on `about:history`, grouped by day, newest first.
+    ///
+    /// If `query` is given, only visits whose title or URL contains it are listed.
+    pub fn to_menu(&self, query: Option<&str>) -> Vec<MenuEntry> {
+        let query = query.map(|query| query.to_lowercase());
+        let matches = |visit: &&Visit| match &query {
+            Some(query) => {
+                visit.title.to_lowercase().contains(query)
+                    || visit.url.as_str().to_lowercase().contains(query)
+            }
+            None => true,
+        };
+
+        let mut entries = Vec::new();
+        match &query {
+            Some(query) => entries.push(MenuEntry::Information(format!(
+                "History matching \"{}\"",
+                query
+            ))),
+            None => entries.push(MenuEntry::Information("History".to_owned())),
+        }
+
+        let mut day = None;
+        for visit in self.visits.iter().rev().filter(matches) {
+            let date = visit.time.format("%A, %Y-%m-%d").to_string();
+            if day.as_ref() != Some(&date) {
+                entries.push(MenuEntry::Information(format!("\n{}", date)));
+                day = Some(date);
+            }
+            entries.push(MenuEntry::Link(
+                visit.item_type,
+                format!("{}  {}", visit.time.format("%H:%M"), visit.title),
+                visit.url.to_string(),
+            ));
+        }
+        if day.is_none() {
+            entries.push(MenuEntry::Information("Nothing found.".to_owned()));
+        }
+        entries
+    }
+}
+
+/// A title for the page: the first line of text in a menu, or the end of the selector.
+pub fn title(response: &Response) -> String {
+    if let ResponseKind::Menu(entries) = &response.kind {
+        let first_line = entries.iter().find_map(|entry| match entry {
+            MenuEntry::Information(text) => text
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(str::to_owned),
+            MenuEntry::Link(..) => None,
+        });
+        if let Some(line) = first_line {
+            return line;
+        }
+    }
+    default_title(&response.url)
+}
+
+fn parse_line(line: &str) -> Option<Visit> {
+    let mut parts = line.splitn(4, '\t');
+    let time = Local
+        .timestamp_opt(parts.next()?.parse().ok()?, 0)
+        .single()?;
+    let item_type = ItemType::decode(*parts.next()?.as_bytes().first()?);
+    let url = Url::parse(parts.next()?).ok()?;
+    let title = parts.next().unwrap_or("").to_owned();
+    Some(Visit {
+        time,
+        url,
+        item_type,
+        title,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(lines: &str) -> History {
+        History {
+            path: None,
+            visits: lines.lines().filter_map(parse_line).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_lines() {
+        let visit =
+            parse_line("1550000000\t1\tgopher://example.com/1/\tA title\twith a tab").unwrap();
+        assert_eq!(visit.time.timestamp(), 1_550_000_000);
+        assert_eq!(visit.item_type, ItemType::Dir);
+        assert_eq!(visit.url.as_str(), "gopher://example.com/1/");
+        assert_eq!(visit.title, "A title\twith a tab");
+    }
+
+    #[test]
+    fn skips_bad_lines() {
+        assert!(parse_line("").is_none());
+        assert!(parse_line("yesterday\t1\tgopher://example.com/1/\tTitle").is_none());
+        assert!(parse_line("1550000000\t1\tnot a url\tTitle").is_none());
+        assert!(parse_line("1550000000\t1\tgopher://example.com/1/").is_some());
+    }
+
+    #[test]
+    fn urls_are_unique_and_newest_first() {
+        let history = history(
+            "1550000000\t1\tgopher://a.org/1/\tA\n\
+             1550000001\t1\tgopher://b.org/1/\tB\n\
+             1550000002\t1\tgopher://a.org/1/\tA again\n",
+        );
+        let urls = history
+            .urls()
+            .into_iter()
+            .map(Url::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(urls, vec!["gopher://a.org/1/", "gopher://b.org/1/"]);
+    }
+}
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use futures::Future;
+use gopher::{Client, Metrics, Request};
+
+use crate::about;
+use crate::render;
+
+/// How much of every response is kept to be looked at, since all of them are kept for as long
+/// as the window is open.
+pub const MAX_KEPT: usize = 64 * 1024;
+
+/// The titles of the columns of the network inspector.
+pub const COLUMNS: [&str; 9] = [
+    "URL",
+    "Type",
+    "Address",
+    "Selector",
+    "Received",
+    "Connect",
+    "First byte",
+    "Complete",
+    "Outcome",
+];
+
+/// A request for a page, and how it went.
+#[derive(Debug)]
+pub struct Exchange {
+    pub metrics: Metrics,
+    /// The start of the response, or why the request failed.
+    pub response: Result<Vec<u8>, String>,
+    /// Whether the page came from the cache instead of the network.
+    pub cached: bool,
+}
+
+impl Exchange {
+    pub fn new(metrics: Metrics, response: Result<&[u8], String>) -> Self {
+        let response = response.map(|buf| buf[..buf.len().min(MAX_KEPT)].to_vec());
+        Exchange {
+            metrics,
+            response,
+            cached: false,
+        }
+    }
+
+    /// A request answered from the cache with `body`.
+    pub fn cached(request: &Request, body: &[u8]) -> Self {
+        let mut metrics = Metrics::new(request);
+        metrics.received = body.len();
+        Exchange {
+            cached: true,
+            ..Exchange::new(metrics, Ok(body))
+        }
+    }
+
+    /// The selector that was sent, without the line ending.
+    pub fn selector(&self) -> String {
+        let request = String::from_utf8_lossy(&self.metrics.request);
+        request.trim_end_matches(['\r', '\n']).to_owned()
+    }
+
+    /// The exchange as a row of the inspector, in the order of `COLUMNS`.
+    pub fn columns(&self) -> [String; 9] {
+        let metrics = &self.metrics;
+        let outcome = match &self.response {
+            Ok(_) if self.cached => "Cached".to_owned(),
+            Ok(_) => "OK".to_owned(),
+            Err(err) => err.clone(),
+        };
+        [
+            metrics.url.to_string(),
+            render::tag(metrics.item_type),
+            metrics
+                .address
+                .map(|addr| addr.to_string())
+                .unwrap_or_else(|| "-".to_owned()),
+            self.selector(),
+            about::format_size(metrics.received),
+            format_time(metrics.connected),
+            format_time(metrics.first_byte),
+            format_time(metrics.completed),
+            outcome,
+        ]
+    }
+}
+
+/// Fetches a page like `Client::fetch_metrics`, passing how the exchange went to `inspect`
+/// whether it succeeds or not.
+pub fn fetch<P, I>(
+    request: Request,
+    timeout: Duration,
+    progress: P,
+    inspect: I,
+) -> impl Future<Item = Vec<u8>, Error = gopher::Error>
+where
+    P: FnMut(usize) + Send + 'static,
+    I: FnOnce(Exchange) + Send + 'static,
+{
+    Client::fetch_metrics(request, timeout, progress).then(move |result| {
+        let (result, metrics) = match result {
+            Ok((buf, metrics)) => (Ok(buf), metrics),
+            Err((err, metrics)) => (Err(err), *metrics),
+        };
+        let response = match &result {
+            Ok(buf) => Ok(&buf[..]),
+            Err(err) => Err(err.to_string()),
+        };
+        inspect(Exchange::new(metrics, response));
+        result
+    })
+}
+
+/// How long a step of a request took, or "-" if it didn't get that far.
+fn format_time(time: Option<Duration>) -> String {
+    match time {
+        Some(time) => format!("{} ms", time.as_millis()),
+        None => "-".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::Url;
+
+    #[test]
+    fn cache_hits_are_marked_as_cached() {
+        let url = Url::parse("gopher://example.com/0/notes.txt").unwrap();
+        let request = Request::from_url(url).unwrap();
+        let exchange = Exchange::cached(&request, b"notes");
+        let columns = exchange.columns();
+        assert_eq!(columns[3], "notes.txt");
+        assert_eq!(columns[5], "-");
+        assert_eq!(columns[8], "Cached");
+        assert_eq!(exchange.metrics.received, 5);
+        assert_eq!(exchange.response, Ok(b"notes".to_vec()));
+    }
+}
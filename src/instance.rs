@@ -0,0 +1,60 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::thread;
+
+use url::Url;
+
+/// Hands `urls` to the window already running on `socket`.
+///
+/// Returns false if there is no such window, in which case this process should open one.
+pub fn send(socket: &Path, urls: &[Url]) -> bool {
+    let mut stream = match UnixStream::connect(socket) {
+        Ok(stream) => stream,
+        Err(_) => return false,
+    };
+    for url in urls {
+        if let Err(err) = writeln!(stream, "{}", url) {
+            warn!("Could not send {} to the running window: {}", url, err);
+            return false;
+        }
+    }
+    true
+}
+
+/// Listens on `socket` for URLs sent by later invocations, calling `open` with each batch.
+///
+/// An empty batch means the browser was started without URLs.
+pub fn listen<F>(socket: &Path, open: F) -> io::Result<()>
+where
+    F: Fn(Vec<Url>) + Send + 'static,
+{
+    // left behind by a browser that didn't exit cleanly
+    if socket.exists() {
+        fs::remove_file(socket)?;
+    }
+    if let Some(parent) = socket.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(socket)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    error!("Error accepting connection: {}", err);
+                    continue;
+                }
+            };
+            let urls = BufReader::new(stream)
+                .lines()
+                .filter_map(|line| line.ok())
+                .filter_map(|line| Url::parse(line.trim()).ok())
+                .collect();
+            open(urls);
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use encoding_rs::Encoding;
+use gopher::gophermap::{self, Line, Warning};
+use gopher::{decode_text, ItemType, MenuEntry, Request, Response, ResponseKind};
+use url::Url;
+
+use crate::errors::Error;
+use crate::mirror::STATE_FILE;
+
+/// Name of the file that holds the menu for a directory.
+pub const GOPHERMAP: &str = "gophermap";
+
+/// Opens a `file://` URL.
+///
+/// Directories are shown through their gophermap if they have one, and as a plain listing
+/// otherwise. Selectors without a host in a gophermap refer to the local tree: absolute ones
+/// start at the root of the tree (the nearest directory with a mirror state file), relative
+/// ones at the directory of the gophermap.
+pub fn open(url: &Url, encodings: &[&'static Encoding]) -> Result<Response, Error> {
+    let path = url
+        .to_file_path()
+        .map_err(|_| Error::InvalidUrl(url.to_string()))?;
+
+    let map_path = if path.is_dir() {
+        let map_path = path.join(GOPHERMAP);
+        if !map_path.exists() {
+            return Ok(menu(url, list_dir(&path)?));
+        }
+        map_path
+    } else {
+        path
+    };
+
+    let buf = fs::read(&map_path)?;
+    if map_path.file_name().and_then(|name| name.to_str()) != Some(GOPHERMAP) {
+        return Response::decode(url.clone(), ItemType::File, buf, encodings).map_err(Error::from);
+    }
+
+    let dir = map_path.parent().unwrap_or_else(|| Path::new("/"));
+    let root = find_root(dir);
+    let text = decode_text(&buf, encodings);
+
+    let mut entries = Vec::new();
+    let mut current = Vec::new();
+    for line in gophermap::parse(&text) {
+        if line.is_info() {
+            current.push(line.display);
+            continue;
+        } else if !current.is_empty() {
+            entries.push(MenuEntry::Information(current.join("\n")));
+            current.clear();
+        }
+
+        if let Some(link) = resolve(&line, &root, dir) {
+            entries.push(MenuEntry::Link(
+                line.item_type,
+                line.display,
+                link.to_string(),
+            ));
+        }
+    }
+    if !current.is_empty() {
+        entries.push(MenuEntry::Information(current.join("\n")));
+    }
+    Ok(menu(url, entries))
+}
+
+/// The gophermap file a `file://` URL is shown through, if any.
+pub fn gophermap_path(url: &Url) -> Option<PathBuf> {
+    let path = url.to_file_path().ok()?;
+    let map_path = if path.is_dir() {
+        path.join(GOPHERMAP)
+    } else {
+        path
+    };
+    let is_gophermap = map_path.file_name().and_then(|name| name.to_str()) == Some(GOPHERMAP);
+    if is_gophermap && map_path.is_file() {
+        Some(map_path)
+    } else {
+        None
+    }
+}
+
+/// Checks the gophermap at `map_path` for lines that won't show the way they were meant to.
+pub fn lint(map_path: &Path, encodings: &[&'static Encoding]) -> Result<Vec<Warning>, Error> {
+    let text = decode_text(&fs::read(map_path)?, encodings);
+    Ok(gophermap::lint(&text))
+}
+
+/// Finds the root of the local tree that contains `dir`.
+pub fn find_root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(STATE_FILE).exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// The URL a gophermap line points at, resolving selectors without a host against the tree.
+pub fn resolve(line: &Line, root: &Path, dir: &Path) -> Option<Url> {
+    if line.host.is_some() {
+        return line.url();
+    }
+
+    let path = if line.selector.starts_with('/') {
+        root.join(line.selector.trim_start_matches('/'))
+    } else {
+        dir.join(&line.selector)
+    };
+    Url::from_file_path(path).ok()
+}
+
+/// Saves a downloaded file to `dir`, named after its selector, returning where it went.
+pub fn save_download(dir: &Path, url: &Url, buf: &[u8]) -> Result<PathBuf, Error> {
+    fs::create_dir_all(dir)?;
+
+    let name = Request::from_url(url.clone())
+        .ok()
+        .and_then(|request| request.file_name())
+        .unwrap_or_else(|| "download".to_owned());
+
+    // don't overwrite earlier downloads
+    let mut path = dir.join(&name);
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}.{}", name, n));
+        n += 1;
+    }
+    fs::write(&path, buf)?;
+    Ok(path)
+}
+
+/// Guesses the item type of a file from its extension, looking at the start of the file if the
+/// extension isn't known.
+pub fn item_type(path: &Path) -> ItemType {
+    if path.is_dir() {
+        return ItemType::Dir;
+    }
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    match extension.as_ref().map(String::as_str) {
+        Some("txt") | Some("text") | Some("md") | Some("log") | Some("csv") | Some("gmi")
+        | Some("conf") | Some("ini") | Some("toml") | Some("json") | Some("rs") | Some("c")
+        | Some("h") | Some("py") | Some("sh") => ItemType::File,
+        Some("html") | Some("htm") => ItemType::Other(b'h'),
+        Some("gif") => ItemType::Gif,
+        Some("png") | Some("jpg") | Some("jpeg") | Some("bmp") | Some("webp") | Some("svg")
+        | Some("ico") | Some("tif") | Some("tiff") => ItemType::Image,
+        Some("hqx") => ItemType::BinHex,
+        Some("uu") | Some("uue") => ItemType::Uuencoded,
+        Some("zip") | Some("arj") | Some("lzh") | Some("exe") | Some("com") => ItemType::Dos,
+        Some("wav") | Some("mp3") | Some("ogg") | Some("flac") => ItemType::Other(b's'),
+        Some("pdf") => ItemType::Other(b'd'),
+        _ => {
+            // text files often have no extension, like README
+            let mut start = Vec::new();
+            let read = fs::File::open(path)
+                .and_then(|file| file.take(1024).read_to_end(&mut start))
+                .is_ok();
+            let text = read && !start.contains(&0) && is_utf8_prefix(&start);
+            if text {
+                ItemType::File
+            } else {
+                ItemType::Binary
+            }
+        }
+    }
+}
+
+/// Whether `buf` is valid UTF-8, except maybe for a character cut off at the end.
+fn is_utf8_prefix(buf: &[u8]) -> bool {
+    match std::str::from_utf8(buf) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    }
+}
+
+fn menu(url: &Url, entries: Vec<MenuEntry>) -> Response {
+    Response {
+        url: url.clone(),
+        item_type: ItemType::Dir,
+        kind: ResponseKind::Menu(entries),
+    }
+}
+
+fn list_dir(path: &Path) -> Result<Vec<MenuEntry>, Error> {
+    let mut children = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| !name.starts_with('.'))
+                .unwrap_or(false)
+        })
+        .collect::<Vec<_>>();
+    children.sort();
+
+    let entries = children
+        .into_iter()
+        .filter_map(|child| {
+            let name = child.file_name()?.to_string_lossy().into_owned();
+            let item_type = item_type(&child);
+            let url = Url::from_file_path(&child).ok()?;
+            Some(MenuEntry::Link(item_type, name, url.to_string()))
+        })
+        .collect();
+    Ok(entries)
+}
@@ -8,27 +8,97 @@ extern crate relm_derive;
 #[macro_use]
 mod utils;
 
+mod about;
+mod args;
+mod bookmarks;
+mod cache;
+mod check;
+mod config;
+mod convert;
 mod errors;
 mod events;
-mod gopher_async;
+mod fetch;
+mod filter;
+mod find;
+mod history;
+mod inspector;
+mod instance;
+mod local;
+mod mirror;
+mod profile;
+mod render;
+mod serve;
+mod source;
+mod tui;
 mod window;
 
 use std::env;
-use std::sync::Arc;
+use std::process;
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use futures::sync::{mpsc, oneshot};
-use futures::{Future, Stream};
+use futures::{future, future::Either, Future, Stream};
 use gio::prelude::*;
 use relm::{Channel, Widget};
+use structopt::StructOpt;
 use tokio::runtime::Runtime;
 
+use crate::args::{Args, Command};
+use crate::cache::Cache;
+use crate::config::Config;
 use crate::errors::Error;
-use crate::events::{Event, Reply};
-use crate::window::Window;
+use crate::events::{Event, Reply, Sender};
+use crate::inspector::Exchange;
+use crate::profile::Profile;
+use crate::window::{Params, Window};
+
+/// How often the progress of a request is reported, at most.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
 fn main() {
-    env_logger::init();
+    let args = Args::from_args();
+    // the terminal front-end shows errors itself, log messages would draw over it
+    if !args.tui || env::var_os("RUST_LOG").is_some() {
+        env_logger::init();
+    }
+
+    let profile = match &args.profile {
+        Some(dir) => Profile::in_dir(dir),
+        None => Profile::default(),
+    };
+
+    // a broken config file shouldn't keep the browser from starting, the window reports it
+    let config_path = args.config.clone().or_else(|| profile.config_path());
+    let (config, config_error) = match config_path.as_ref().map(|path| Config::load(path)) {
+        Some(Ok(config)) => (config, None),
+        Some(Err(err)) => {
+            error!("{}", err);
+            (Config::default(), Some(err))
+        }
+        None => (Config::default(), None),
+    };
+
+    match &args.command {
+        Some(Command::Fetch(fetch_args)) => process::exit(fetch::run(fetch_args, &config)),
+        Some(Command::Serve(serve_args)) => process::exit(serve::run(serve_args, &config)),
+        Some(Command::Check(check_args)) => process::exit(check::run(check_args, &config)),
+        None => (),
+    }
+
+    // private windows are on their own, so they don't share a socket either
+    let instance_socket = if args.private || args.new_window || args.tui {
+        None
+    } else {
+        profile.instance_socket()
+    };
+    if let Some(socket) = &instance_socket {
+        if instance::send(socket, &args.urls) {
+            info!("Opened in the running window.");
+            process::exit(0);
+        }
+    }
 
     let mut runtime = Runtime::new().expect("failed to create runtime");
     let (stop_tx, stop_rx) = oneshot::channel::<()>();
@@ -38,19 +108,123 @@ fn main() {
 
     let gui_tx = Arc::new(gui_tx);
 
+    let cache_dir = if config.cache.disk && !args.private {
+        profile.cache_dir()
+    } else {
+        None
+    };
+    let cache = Arc::new(Mutex::new(Cache::new(
+        config.cache.capacity,
+        cache_dir,
+        config.cache.disk_limit_mib * 1024 * 1024,
+        config.default_port,
+    )));
+    let evl_cache = cache.clone();
+    let config = Arc::new(Mutex::new(config));
+    let evl_config = config.clone();
+
     let evl = evl_rx
         .map_err(|_| Error::ChannelRecv)
         .for_each(move |event| match event {
             Event::MakeRequest(request, sender) => {
-                use crate::gopher_async::Client;
+                use gopher::Response;
                 let gui_tx = gui_tx.clone();
-                Client::request_async(request).and_then(move |response| {
-                    sender
-                        .lock()
-                        .unwrap()
-                        .send(Reply::Response(response))
-                        .map_err(Error::from)
+                let (timeout, encodings) = {
+                    let config = evl_config.lock().unwrap();
+                    (config.timeout(), config.encodings())
+                };
+
+                let (cached, offline) = {
+                    let mut cache = evl_cache.lock().unwrap();
+                    if cache.is_offline() {
+                        (cache.get_any(&request.url), true)
+                    } else {
+                        (cache.get(&request.url), false)
+                    }
+                };
+                let response = match cached {
+                    Some(entry) => {
+                        debug!("Cache hit for {}", entry.url);
+                        report_exchanges(&sender)(Exchange::cached(&request, &entry.body));
+                        let fetched = entry.fetched;
+                        let response = entry
+                            .to_response(request.url, request.item_type, &encodings)
+                            .map(|response| (response, Some(fetched)));
+                        Either::A(future::result(response))
+                    }
+                    None if offline => Either::A(future::err(Error::Offline(request.url))),
+                    None => {
+                        let cache = evl_cache.clone();
+                        let url = request.url.clone();
+                        let item_type = request.item_type;
+                        let progress_sender = sender.clone();
+                        let progress_url = url.clone();
+                        let mut reported: Option<Instant> = None;
+                        let progress = move |bytes| {
+                            // a reply for every chunk would swamp the window on large files
+                            if reported.map_or(false, |at| at.elapsed() < PROGRESS_INTERVAL) {
+                                return;
+                            }
+                            reported = Some(Instant::now());
+                            let reply = Reply::Progress(progress_url.clone(), bytes);
+                            if let Err(err) = progress_sender.send(reply) {
+                                debug!("Could not report progress: {}", err);
+                            }
+                        };
+                        let inspect = report_exchanges(&sender);
+                        let fetch = inspector::fetch(request, timeout, progress, inspect);
+                        Either::B(fetch.from_err().and_then(move |buf| {
+                            cache
+                                .lock()
+                                .unwrap()
+                                .insert(url.clone(), item_type, buf.clone());
+                            Response::decode(url, item_type, buf, &encodings)
+                                .map(|response| (response, None))
+                                .map_err(Error::from)
+                        }))
+                    }
+                };
+
+                // report failures to the window instead of stopping the event loop
+                let fail_sender = sender.clone();
+                let response = response
+                    .and_then(move |(response, fetched)| {
+                        sender.send(Reply::Response(response, fetched))
+                    })
+                    .or_else(move |err| {
+                        error!("Request failed: {}", err);
+                        fail_sender.send(Reply::Fail(Box::new(err)))
+                    });
+                Either::A(response)
+            }
+            Event::Mirror(url, options, sender) => {
+                let fail_sender = sender.clone();
+                let inspect = report_exchanges(&sender);
+                let crawl = mirror::mirror(url, options, inspect, move |progress| {
+                    sender.send(Reply::MirrorProgress(progress))
+                })
+                .or_else(move |err| {
+                    error!("Mirror failed: {}", err);
+                    fail_sender.send(Reply::Fail(Box::new(err)))
                 })
+                .map_err(|err| error!("Error: {:?}", err));
+
+                // crawling takes a while, don't hold up other requests
+                tokio::spawn(crawl);
+                Either::B(future::ok(()))
+            }
+            Event::CheckLinks(url, options, sender) => {
+                let fail_sender = sender.clone();
+                let check = check::check_url(url.clone(), options, report_exchanges(&sender))
+                    .and_then(move |reports| sender.send(Reply::LinkReports(url, reports)))
+                    .or_else(move |err| {
+                        error!("Link check failed: {}", err);
+                        fail_sender.send(Reply::Fail(Box::new(err)))
+                    })
+                    .map_err(|err| error!("Error: {:?}", err));
+
+                tokio::spawn(check);
+                Either::B(future::ok(()))
             }
         })
         .map_err(|err| {
@@ -58,10 +232,52 @@ fn main() {
         });
     runtime.spawn(evl);
 
-    thread::spawn(move || {
-        Window::run((stop_tx, evl_tx)).expect("error");
-    });
+    let history_path = if args.private {
+        None
+    } else {
+        profile.history_path()
+    };
+    if args.tui {
+        let params = tui::Params {
+            stop_tx,
+            evl_tx,
+            cache,
+            config,
+            config_error,
+            bookmarks_path: profile.bookmarks_path(),
+            history_path,
+            urls: args.urls,
+        };
+        thread::spawn(move || tui::run(params));
+    } else {
+        let params = Params {
+            stop_tx,
+            evl_tx,
+            cache,
+            config,
+            config_path,
+            config_error,
+            bookmarks_path: profile.bookmarks_path(),
+            history_path,
+            private: args.private,
+            instance_socket,
+            urls: args.urls,
+        };
+        thread::spawn(move || {
+            Window::run(params).expect("error");
+        });
+    }
 
     runtime.block_on(stop_rx);
     info!("Exiting.");
 }
+
+/// Passes exchanges on to the network inspector of whoever sent an event.
+fn report_exchanges(sender: &Sender) -> impl Fn(Exchange) + Clone + Send + 'static {
+    let sender = sender.clone();
+    move |exchange| {
+        if let Err(err) = sender.send(Reply::Exchange(Box::new(exchange))) {
+            debug!("Could not report the exchange: {}", err);
+        }
+    }
+}
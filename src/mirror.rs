@@ -0,0 +1,534 @@
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use futures::future::{self, Either, Loop};
+use futures::Future;
+use gopher::gophermap::{self, Line};
+use gopher::{ItemType, Request};
+use tokio::timer::Delay;
+use url::Url;
+
+use crate::cache;
+use crate::errors::Error;
+use crate::inspector::{self, Exchange};
+use crate::local::GOPHERMAP;
+
+/// Name of the file that marks the root of a mirrored tree and records how it was made.
+pub const STATE_FILE: &str = ".gopher-mirror";
+
+/// Directory in the root of a tree that everything but menus is stored under.
+const ITEMS_DIR: &str = "_items";
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// How many menus deep to follow links from the starting menu.
+    pub depth: usize,
+    /// Stop downloading once the tree holds this many bytes.
+    pub max_bytes: u64,
+    /// Time to wait before each request, to go easy on the server.
+    pub delay: Duration,
+    /// Time after which a request is given up on.
+    pub timeout: Duration,
+    /// The port of URLs without one.
+    pub default_port: u16,
+    /// Directory under which the tree is stored, in a subdirectory named after the host.
+    pub dest: PathBuf,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            depth: 3,
+            max_bytes: 50 * 1024 * 1024,
+            delay: Duration::from_secs(1),
+            timeout: Duration::from_secs(30),
+            default_port: 70,
+            dest: dirs::data_dir()
+                .unwrap_or_default()
+                .join("gopher-browser")
+                .join("mirrors"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub root: PathBuf,
+    pub current: Option<Url>,
+    pub saved: usize,
+    pub failed: usize,
+    pub queued: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub finished: bool,
+}
+
+/// The directory a mirror of the host of `url` is stored in. Hosts on a port other than the
+/// standard one get it in the name, whatever the default port is.
+pub fn root_dir(url: &Url, options: &Options) -> PathBuf {
+    let host = url.host_str().unwrap_or("localhost").to_lowercase();
+    match url.port().unwrap_or(options.default_port) {
+        70 => options.dest.join(host),
+        port => options.dest.join(format!("{}_{}", host, port)),
+    }
+}
+
+/// Saves every item reachable from the menu at `start` on the same host.
+///
+/// Items that are already in the tree are read back instead of downloaded again, so an
+/// interrupted mirror resumes where it left off. `report` is called after every item, and
+/// `inspect` with every request made over the network.
+pub fn mirror<I, F>(
+    start: Url,
+    options: Options,
+    inspect: I,
+    report: F,
+) -> impl Future<Item = (), Error = Error>
+where
+    I: Fn(Exchange) + Clone + Send + 'static,
+    F: Fn(Progress) -> Result<(), Error> + Send + 'static,
+{
+    future::result(Crawler::new(start, options, inspect, report))
+        .and_then(|crawler| future::loop_fn(crawler, Crawler::step))
+}
+
+struct Crawler<I, F> {
+    host: String,
+    port: u16,
+    options: Options,
+    queue: VecDeque<(Url, usize)>,
+    seen: HashSet<String>,
+    progress: Progress,
+    inspect: I,
+    report: F,
+}
+
+impl<I, F> Crawler<I, F>
+where
+    I: Fn(Exchange) + Clone + Send + 'static,
+    F: Fn(Progress) -> Result<(), Error> + Send + 'static,
+{
+    fn new(start: Url, options: Options, inspect: I, report: F) -> Result<Self, Error> {
+        let host = match start.host_str() {
+            Some(host) => host.to_lowercase(),
+            None => return Err(Error::InvalidUrl(start.to_string())),
+        };
+        let port = start.port().unwrap_or(options.default_port);
+
+        let root = root_dir(&start, &options);
+        fs::create_dir_all(&root)?;
+        let state = format!(
+            "start={}\ndepth={}\nmax_bytes={}\ndelay_ms={}\n",
+            start,
+            options.depth,
+            options.max_bytes,
+            options.delay.as_millis()
+        );
+        fs::write(root.join(STATE_FILE), state)?;
+
+        let progress = Progress {
+            root,
+            current: None,
+            saved: 0,
+            failed: 0,
+            queued: 1,
+            bytes: 0,
+            max_bytes: options.max_bytes,
+            finished: false,
+        };
+
+        let mut seen = HashSet::new();
+        seen.insert(cache::normalize(&start, options.default_port));
+        let mut queue = VecDeque::new();
+        queue.push_back((start, 0));
+
+        Ok(Crawler {
+            host,
+            port,
+            options,
+            queue,
+            seen,
+            progress,
+            inspect,
+            report,
+        })
+    }
+
+    fn step(mut self) -> Box<dyn Future<Item = Loop<(), Self>, Error = Error> + Send> {
+        let (url, depth) = match self.queue.pop_front() {
+            Some(next) if self.progress.bytes < self.options.max_bytes => next,
+            _ => {
+                self.progress.current = None;
+                self.progress.finished = true;
+                let result = (self.report)(self.progress.clone()).map(|_| Loop::Break(()));
+                return Box::new(future::result(result));
+            }
+        };
+
+        let request = match Request::with_default_port(url.clone(), self.options.default_port) {
+            Ok(request) => request,
+            Err(err) => {
+                warn!("Skipping {}: {}", url, err);
+                self.progress.failed += 1;
+                return Box::new(future::ok(Loop::Continue(self)));
+            }
+        };
+        let item_type = request.item_type;
+        let path = match self.local_path(&request) {
+            Some(path) => path,
+            None => {
+                warn!("Skipping {}: no local path", url);
+                self.progress.failed += 1;
+                return Box::new(future::ok(Loop::Continue(self)));
+            }
+        };
+        self.progress.current = Some(url.clone());
+
+        let body = if path.exists() {
+            // saved by an earlier run
+            Either::A(future::result(fs::read(&path).map(|buf| (buf, false))).from_err())
+        } else {
+            let delay = Delay::new(Instant::now() + self.options.delay)
+                .map_err(|err| Error::from(io::Error::other(err)));
+            let timeout = self.options.timeout;
+            let inspect = self.inspect.clone();
+            Either::B(
+                delay
+                    .and_then(move |_| {
+                        inspector::fetch(request, timeout, |_| (), inspect).from_err()
+                    })
+                    .map(|buf| (buf, true)),
+            )
+        };
+
+        Box::new(body.then(move |result| {
+            let stored = result.and_then(|(buf, fresh)| {
+                if fresh {
+                    self.store(&path, item_type, &buf).map(|_| (buf, fresh))
+                } else {
+                    Ok((buf, fresh))
+                }
+            });
+            match stored {
+                Ok((buf, fresh)) => {
+                    self.progress.saved += 1;
+                    self.progress.bytes += buf.len() as u64;
+                    if item_type == ItemType::Dir && depth < self.options.depth {
+                        // menus read back from the tree point at where their items are stored
+                        self.enqueue(&String::from_utf8_lossy(&buf), depth + 1, !fresh);
+                    }
+                }
+                Err(err) => {
+                    warn!("Could not save {}: {}", url, err);
+                    self.progress.failed += 1;
+                }
+            }
+
+            self.progress.queued = self.queue.len();
+            (self.report)(self.progress.clone())?;
+            Ok(Loop::Continue(self))
+        }))
+    }
+
+    /// Writes an item to the tree, with the links of menus rewritten to point into it.
+    fn store(&self, path: &Path, item_type: ItemType, buf: &[u8]) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if item_type == ItemType::Dir {
+            fs::write(path, self.rewrite(&String::from_utf8_lossy(buf)))?;
+        } else {
+            fs::write(path, buf)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the host and port from links on this server and points them at where the items are
+    /// stored, so that the stored gophermap refers to the local tree both when opened from disk
+    /// and when served by another server.
+    fn rewrite(&self, menu: &str) -> String {
+        let mut text = String::new();
+        for mut line in gophermap::parse(menu) {
+            if self.is_local(&line) {
+                line.host = None;
+                line.port = None;
+                // point at where the item is stored, relative to the root of the tree
+                let stored = if is_downloadable(line.item_type) {
+                    item_segments(line.item_type, &line.selector)
+                } else {
+                    None
+                };
+                match stored {
+                    Some(segments) => line.selector = format!("/{}", segments.join("/")),
+                    None if !line.selector.starts_with('/') => line.selector.insert(0, '/'),
+                    None => (),
+                }
+            }
+            text.push_str(&line.to_string());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Queues the items on this server that a menu links to. If the menu was read back from the
+    /// tree, `stored` is set and its selectors are turned back into the ones the server gave.
+    fn enqueue(&mut self, menu: &str, depth: usize, stored: bool) {
+        for line in gophermap::parse(menu) {
+            if !self.is_local(&line) || !is_downloadable(line.item_type) {
+                continue;
+            }
+
+            let selector = if stored {
+                match server_selector(line.item_type, &line.selector) {
+                    Some(selector) => selector,
+                    None => continue,
+                }
+            } else {
+                line.selector
+            };
+            let url =
+                match gophermap::selector_url(&self.host, self.port, line.item_type, &selector) {
+                    Some(url) => url,
+                    None => continue,
+                };
+            let key = cache::normalize(&url, self.options.default_port);
+            if self.seen.insert(key) {
+                self.queue.push_back((url, depth));
+            }
+        }
+    }
+
+    fn is_local(&self, line: &Line) -> bool {
+        if line.is_info() {
+            return false;
+        }
+        match &line.host {
+            Some(host) => {
+                host.eq_ignore_ascii_case(&self.host)
+                    && line.port.unwrap_or(self.options.default_port) == self.port
+            }
+            None => true,
+        }
+    }
+
+    fn local_path(&self, request: &Request) -> Option<PathBuf> {
+        item_path(&self.progress.root, request.item_type, &request.selector())
+    }
+}
+
+/// Where an item is stored in the tree, relative to its root, as path segments.
+///
+/// Menus are directories holding a gophermap, and everything else is kept under `ITEMS_DIR` in a
+/// directory for its type, so that a file and a menu with the same selector don't end up at the
+/// same path. Names that could be taken for one of those, or for the state file, start with an
+/// extra "_".
+fn item_segments(item_type: ItemType, selector: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    if item_type != ItemType::Dir {
+        segments.push(ITEMS_DIR.to_owned());
+        segments.push((item_type.encode() as char).to_string());
+    }
+    for segment in selector.split('/').filter(|segment| !segment.is_empty()) {
+        if segment == "." || segment == ".." {
+            return None;
+        }
+        if segment.starts_with('_') || segment.starts_with('.') || segment == GOPHERMAP {
+            segments.push(format!("_{}", segment));
+        } else {
+            segments.push(segment.to_owned());
+        }
+    }
+    if item_type != ItemType::Dir && segments.len() == 2 {
+        // a file needs a name
+        return None;
+    }
+    Some(segments)
+}
+
+/// The selector of an item, from where a menu in the tree points at it: the reverse of
+/// `item_segments`.
+fn server_selector(item_type: ItemType, stored: &str) -> Option<String> {
+    let mut segments = stored.split('/').filter(|segment| !segment.is_empty());
+    if item_type != ItemType::Dir {
+        let items_dir = segments.next()?;
+        let type_dir = segments.next()?;
+        if items_dir != ITEMS_DIR || type_dir != (item_type.encode() as char).to_string() {
+            return None;
+        }
+    }
+    let segments = segments
+        .map(|segment| segment.strip_prefix('_').unwrap_or(segment))
+        .collect::<Vec<_>>();
+    Some(format!("/{}", segments.join("/")))
+}
+
+/// The file an item is stored in.
+fn item_path(root: &Path, item_type: ItemType, selector: &str) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+    path.extend(item_segments(item_type, selector)?);
+    if item_type == ItemType::Dir {
+        path.push(GOPHERMAP);
+    }
+    Some(path)
+}
+
+fn is_downloadable(item_type: ItemType) -> bool {
+    match item_type {
+        ItemType::CsoServer
+        | ItemType::Error
+        | ItemType::IndexServer
+        | ItemType::Telnet
+        | ItemType::Tn3270
+        | ItemType::Other(_) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::process;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use tokio::runtime::Runtime;
+
+    use super::*;
+
+    #[test]
+    fn menus_and_files_with_the_same_selector_dont_collide() {
+        let root = Path::new("/mirror");
+        let menu = item_path(root, ItemType::Dir, "/foo").unwrap();
+        let file = item_path(root, ItemType::File, "/foo").unwrap();
+        let nested = item_path(root, ItemType::File, "/foo/bar").unwrap();
+        assert_eq!(menu, Path::new("/mirror/foo/gophermap"));
+        assert_eq!(file, Path::new("/mirror/_items/0/foo"));
+        assert_eq!(nested, Path::new("/mirror/_items/0/foo/bar"));
+        assert!(!menu.starts_with(&file) && !file.starts_with(menu.parent().unwrap()));
+    }
+
+    #[test]
+    fn escapes_reserved_names() {
+        let root = Path::new("/mirror");
+        assert_eq!(
+            item_path(root, ItemType::Dir, "/_items/0").unwrap(),
+            Path::new("/mirror/__items/0/gophermap")
+        );
+        assert_eq!(
+            item_path(root, ItemType::Dir, "gophermap").unwrap(),
+            Path::new("/mirror/_gophermap/gophermap")
+        );
+        assert_eq!(
+            item_path(root, ItemType::Binary, "/.gopher-mirror").unwrap(),
+            Path::new("/mirror/_items/9/_.gopher-mirror")
+        );
+    }
+
+    #[test]
+    fn stored_selectors_map_back_to_the_server() {
+        for &(item_type, selector) in &[
+            (ItemType::Dir, "/_sub/gophermap"),
+            (ItemType::File, "/phlog/.hidden"),
+            (ItemType::Binary, "/_items/9/a"),
+        ] {
+            let segments = item_segments(item_type, selector).unwrap();
+            let stored = format!("/{}", segments.join("/"));
+            assert_eq!(
+                server_selector(item_type, &stored),
+                Some(selector.to_owned())
+            );
+        }
+        assert_eq!(server_selector(ItemType::File, "/notes.txt"), None);
+        assert_eq!(server_selector(ItemType::File, "/_items/9/notes.txt"), None);
+    }
+
+    /// Serves `count` requests from a few fixed items, giving the selectors that were asked for.
+    fn serve(count: usize) -> (u16, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let mut selectors = Vec::new();
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut selector = String::new();
+                BufReader::new(&stream).read_line(&mut selector).unwrap();
+                let selector = selector.trim_end().trim_start_matches('/').to_owned();
+                let link = |line: &str| format!("{}\t127.0.0.1\t{}\r\n", line, port);
+                let body = match selector.as_str() {
+                    "" => link("1Sub\t/_sub") + &link("0Notes\t/notes.txt") + ".\r\n",
+                    "_sub" => link("0Deep\t/_sub/deep.txt") + ".\r\n",
+                    "notes.txt" => "notes".to_owned(),
+                    "_sub/deep.txt" => "deep".to_owned(),
+                    _ => "3Not found\t\terror.host\t1\r\n".to_owned(),
+                };
+                stream.write_all(body.as_bytes()).unwrap();
+                selectors.push(selector);
+            }
+            selectors
+        });
+        (port, handle)
+    }
+
+    /// Mirrors the server on `port`, giving the last progress and how many requests were made.
+    fn crawl(port: u16, dest: &Path) -> (Progress, usize) {
+        let start = Url::parse(&format!("gopher://127.0.0.1:{}/1/", port)).unwrap();
+        let options = Options {
+            delay: Duration::from_millis(0),
+            timeout: Duration::from_secs(5),
+            dest: dest.to_path_buf(),
+            ..Options::default()
+        };
+        let last = Arc::new(Mutex::new(None));
+        let reported = last.clone();
+        let exchanges = Arc::new(Mutex::new(0));
+        let inspected = exchanges.clone();
+        let inspect = move |_: Exchange| *inspected.lock().unwrap() += 1;
+        let crawl = mirror(start, options, inspect, move |progress| {
+            *reported.lock().unwrap() = Some(progress);
+            Ok(())
+        });
+        Runtime::new().unwrap().block_on(crawl).unwrap();
+        let progress = last.lock().unwrap().take();
+        let exchanges = *exchanges.lock().unwrap();
+        (progress.unwrap(), exchanges)
+    }
+
+    #[test]
+    fn crawls_files_under_their_server_selectors() {
+        let dest = std::env::temp_dir().join(format!("gopher-mirror-test-{}", process::id()));
+        let _ = fs::remove_dir_all(&dest);
+        let (port, server) = serve(4);
+        let (progress, exchanges) = crawl(port, &dest);
+        let mut selectors = server.join().unwrap();
+        selectors.sort();
+        assert_eq!(selectors, vec!["", "_sub", "_sub/deep.txt", "notes.txt"]);
+        assert_eq!((progress.saved, progress.failed), (4, 0));
+        assert_eq!(exchanges, 4);
+
+        let read = |path: &str| fs::read_to_string(progress.root.join(path)).unwrap();
+        assert_eq!(read("_items/0/notes.txt"), "notes");
+        assert_eq!(read("_items/0/__sub/deep.txt"), "deep");
+        assert!(read("gophermap").contains("1Sub\t/__sub\n"));
+
+        // a second run reads everything back from the tree without asking the server
+        let (progress, exchanges) = crawl(port, &dest);
+        assert_eq!((progress.saved, progress.failed), (4, 0));
+        assert_eq!(exchanges, 0);
+        fs::remove_dir_all(&dest).unwrap();
+    }
+
+    #[test]
+    fn rejects_paths_out_of_the_tree() {
+        let root = Path::new("/mirror");
+        assert_eq!(item_path(root, ItemType::File, "/../etc/passwd"), None);
+        assert_eq!(item_path(root, ItemType::File, "/"), None);
+        assert_eq!(
+            item_path(root, ItemType::Dir, "/").unwrap(),
+            Path::new("/mirror/gophermap")
+        );
+    }
+}
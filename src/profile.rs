@@ -0,0 +1,56 @@
+use std::path::{Path, PathBuf};
+
+/// Where a browser profile keeps its files.
+///
+/// The default profile follows the XDG base directories. A profile given with `--profile` keeps
+/// everything in one directory instead, so that it shares nothing with the others.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    config_dir: Option<PathBuf>,
+    data_dir: Option<PathBuf>,
+    cache_dir: Option<PathBuf>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        let app_dir = |dir: PathBuf| dir.join("gopher-browser");
+        Profile {
+            config_dir: dirs::config_dir().map(app_dir),
+            data_dir: dirs::data_dir().map(app_dir),
+            cache_dir: dirs::cache_dir().map(app_dir),
+        }
+    }
+}
+
+impl Profile {
+    pub fn in_dir(dir: &Path) -> Self {
+        Profile {
+            config_dir: Some(dir.to_path_buf()),
+            data_dir: Some(dir.to_path_buf()),
+            cache_dir: Some(dir.join("cache")),
+        }
+    }
+
+    pub fn config_path(&self) -> Option<PathBuf> {
+        self.config_dir.as_ref().map(|dir| dir.join("config.toml"))
+    }
+
+    pub fn bookmarks_path(&self) -> Option<PathBuf> {
+        self.data_dir
+            .as_ref()
+            .map(|dir| dir.join("bookmarks.gophermap"))
+    }
+
+    pub fn history_path(&self) -> Option<PathBuf> {
+        self.data_dir.as_ref().map(|dir| dir.join("history.tsv"))
+    }
+
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.cache_dir.clone()
+    }
+
+    /// The socket a running window listens on for URLs from later invocations.
+    pub fn instance_socket(&self) -> Option<PathBuf> {
+        self.data_dir.as_ref().map(|dir| dir.join("instance.sock"))
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt::Write;
+
+use gopher::{ItemType, MenuEntry, Response, ResponseKind};
+
+use crate::render::{tag, Renderer};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const UNDERLINE: &str = "\x1b[4m";
+
+/// Text for a terminal, like `TextRenderer` but with links in color.
+pub struct AnsiRenderer;
+
+impl Renderer for AnsiRenderer {
+    type Output = String;
+
+    fn render(&self, response: &Response) -> String {
+        match &response.kind {
+            ResponseKind::Menu(entries) => {
+                let mut out = String::new();
+                for entry in entries {
+                    match entry {
+                        MenuEntry::Information(text) => {
+                            for line in text.lines() {
+                                writeln!(out, "       {}", line).unwrap();
+                            }
+                        }
+                        MenuEntry::Link(item_type, label, target) => {
+                            let tag = format!("[{}]", tag(*item_type));
+                            writeln!(
+                                out,
+                                "{}{:<6}{} {}{}{}  {}<{}>{}",
+                                color(*item_type),
+                                tag,
+                                RESET,
+                                UNDERLINE,
+                                label,
+                                RESET,
+                                DIM,
+                                target,
+                                RESET
+                            )
+                            .unwrap();
+                        }
+                    }
+                }
+                out
+            }
+            ResponseKind::TextFile(text) => text.clone(),
+            ResponseKind::BinaryFile(buf) => {
+                format!("{}Binary file, {} bytes{}\n", BOLD, buf.len(), RESET)
+            }
+        }
+    }
+}
+
+/// The escape code for showing links of `item_type`: menus in blue, text in green, downloads in
+/// magenta, and anything else in yellow.
+pub fn color(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::Dir => "\x1b[1;34m",
+        ItemType::File | ItemType::Other(b'h') => "\x1b[1;32m",
+        ItemType::BinHex
+        | ItemType::Dos
+        | ItemType::Uuencoded
+        | ItemType::Binary
+        | ItemType::Gif
+        | ItemType::Image => "\x1b[1;35m",
+        ItemType::Error => "\x1b[1;31m",
+        _ => "\x1b[1;33m",
+    }
+}
@@ -0,0 +1,45 @@
+use std::fmt::Write;
+
+use gopher::{MenuEntry, Response, ResponseKind};
+
+use crate::render::Renderer;
+
+/// Gemini's text format: info text and text files are preformatted, menu links are link lines.
+pub struct GemtextRenderer;
+
+impl Renderer for GemtextRenderer {
+    type Output = String;
+
+    fn render(&self, response: &Response) -> String {
+        let mut out = String::new();
+        match &response.kind {
+            ResponseKind::Menu(entries) => {
+                for entry in entries {
+                    match entry {
+                        MenuEntry::Information(text) => preformatted(&mut out, text),
+                        MenuEntry::Link(_, label, target) => {
+                            writeln!(out, "=> {} {}", target, label).unwrap();
+                        }
+                    }
+                }
+            }
+            ResponseKind::TextFile(text) => preformatted(&mut out, text),
+            ResponseKind::BinaryFile(buf) => {
+                writeln!(out, "=> {} {} bytes", response.url, buf.len()).unwrap();
+            }
+        }
+        out
+    }
+}
+
+fn preformatted(out: &mut String, text: &str) {
+    writeln!(out, "```").unwrap();
+    for line in text.lines() {
+        // there's no escaping in gemtext, and a line starting with ``` would end the block
+        if line.starts_with("```") {
+            write!(out, " ").unwrap();
+        }
+        writeln!(out, "{}", line).unwrap();
+    }
+    writeln!(out, "```").unwrap();
+}
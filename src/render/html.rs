@@ -0,0 +1,86 @@
+use std::fmt::Write;
+
+use gopher::{ItemType, MenuEntry, Response, ResponseKind};
+
+use crate::render::{tag, Renderer};
+
+/// A standalone HTML document, with menus as preformatted text and links.
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    type Output = String;
+
+    fn render(&self, response: &Response) -> String {
+        let url = escape(response.url.as_str());
+        let mut out = String::new();
+        writeln!(out, "<!DOCTYPE html>").unwrap();
+        writeln!(out, "<html>").unwrap();
+        writeln!(out, "<head>").unwrap();
+        writeln!(out, "<meta charset=\"utf-8\">").unwrap();
+        writeln!(out, "<title>{}</title>", url).unwrap();
+        writeln!(out, "</head>").unwrap();
+        writeln!(out, "<body>").unwrap();
+
+        match &response.kind {
+            ResponseKind::Menu(entries) => {
+                writeln!(out, "<pre>").unwrap();
+                for entry in entries {
+                    match entry {
+                        MenuEntry::Information(text) => {
+                            for line in text.lines() {
+                                writeln!(out, "{}", escape(line)).unwrap();
+                            }
+                        }
+                        MenuEntry::Link(item_type, label, target) => {
+                            writeln!(
+                                out,
+                                "<a href=\"{}\" class=\"{}\">{}</a>",
+                                escape(target),
+                                class(*item_type),
+                                escape(label)
+                            )
+                            .unwrap();
+                        }
+                    }
+                }
+                writeln!(out, "</pre>").unwrap();
+            }
+            ResponseKind::TextFile(text) => {
+                writeln!(out, "<pre>{}</pre>", escape(text)).unwrap();
+            }
+            ResponseKind::BinaryFile(buf) => {
+                writeln!(
+                    out,
+                    "<p><a href=\"{}\">{}</a>, {} bytes</p>",
+                    url,
+                    url,
+                    buf.len()
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(out, "</body>").unwrap();
+        writeln!(out, "</html>").unwrap();
+        out
+    }
+}
+
+/// The item type as a CSS class, so that stylesheets can tell links apart.
+fn class(item_type: ItemType) -> String {
+    format!("type-{}", tag(item_type).to_lowercase())
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,69 @@
+use std::fmt::Write;
+
+use gopher::{MenuEntry, Response, ResponseKind};
+
+use crate::render::Renderer;
+
+/// Markdown, with info text and text files as code blocks so that their spacing is kept, and
+/// menu links as a list.
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    type Output = String;
+
+    fn render(&self, response: &Response) -> String {
+        let mut out = String::new();
+        match &response.kind {
+            ResponseKind::Menu(entries) => {
+                let mut last_was_link = None;
+                for entry in entries {
+                    let is_link = match entry {
+                        MenuEntry::Information(_) => false,
+                        MenuEntry::Link(..) => true,
+                    };
+                    // blocks are set apart by blank lines, but the items of a list aren't
+                    match last_was_link {
+                        Some(true) if is_link => (),
+                        Some(_) => writeln!(out).unwrap(),
+                        None => (),
+                    }
+                    match entry {
+                        MenuEntry::Information(text) => code_block(&mut out, text),
+                        MenuEntry::Link(_, label, target) => {
+                            writeln!(out, "- [{}](<{}>)", escape(label), target).unwrap();
+                        }
+                    }
+                    last_was_link = Some(is_link);
+                }
+            }
+            ResponseKind::TextFile(text) => code_block(&mut out, text),
+            ResponseKind::BinaryFile(buf) => {
+                let url = response.url.as_str();
+                writeln!(out, "[{}](<{}>), {} bytes", escape(url), url, buf.len()).unwrap();
+            }
+        }
+        out
+    }
+}
+
+/// A fenced code block, with a fence longer than any run of backticks in the text.
+fn code_block(out: &mut String, text: &str) {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    writeln!(out, "{}", fence).unwrap();
+    for line in text.lines() {
+        writeln!(out, "{}", line).unwrap();
+    }
+    writeln!(out, "{}", fence).unwrap();
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_[]<>".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
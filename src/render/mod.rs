@@ -0,0 +1,51 @@
+//! Turning responses into something to look at.
+//!
+//! Every view of a page implements `Renderer`: the widgets in the browser window, the text, HTML,
+//! Markdown, gemtext and terminal output of `gopher-browser fetch` and "Save page as", and the
+//! pages of `--tui`.
+
+mod ansi;
+mod gemtext;
+mod html;
+mod markdown;
+mod text;
+mod widgets;
+
+use gopher::{ItemType, Response};
+
+pub use self::ansi::{color, AnsiRenderer};
+pub use self::gemtext::GemtextRenderer;
+pub use self::html::HtmlRenderer;
+pub use self::markdown::MarkdownRenderer;
+pub use self::text::TextRenderer;
+pub use self::widgets::{menu_rows, GtkRenderer, MenuRow};
+
+/// Renders a response into some kind of output.
+pub trait Renderer {
+    type Output;
+
+    fn render(&self, response: &Response) -> Self::Output;
+}
+
+/// A short name for the type of an item, in the style of other text gopher clients.
+pub fn tag(item_type: ItemType) -> String {
+    let tag = match item_type {
+        ItemType::File => "TXT",
+        ItemType::Dir => "DIR",
+        ItemType::CsoServer => "CSO",
+        ItemType::Error => "ERR",
+        ItemType::BinHex => "HQX",
+        ItemType::Dos => "DOS",
+        ItemType::Uuencoded => "UUE",
+        ItemType::IndexServer => "QRY",
+        ItemType::Telnet => "TEL",
+        ItemType::Binary => "BIN",
+        ItemType::RedundantServer => "DUP",
+        ItemType::Tn3270 => "3270",
+        ItemType::Gif => "GIF",
+        ItemType::Image => "IMG",
+        ItemType::Other(b'h') => "HTML",
+        ItemType::Other(byte) => return (byte as char).to_string(),
+    };
+    tag.to_owned()
+}
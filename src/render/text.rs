@@ -0,0 +1,36 @@
+use std::fmt::Write;
+
+use gopher::{MenuEntry, Response, ResponseKind};
+
+use crate::render::{tag, Renderer};
+
+/// Plain text: menus get the type and URL of every link, text files are left as they are.
+pub struct TextRenderer;
+
+impl Renderer for TextRenderer {
+    type Output = String;
+
+    fn render(&self, response: &Response) -> String {
+        match &response.kind {
+            ResponseKind::Menu(entries) => {
+                let mut out = String::new();
+                for entry in entries {
+                    match entry {
+                        MenuEntry::Information(text) => {
+                            for line in text.lines() {
+                                writeln!(out, "       {}", line).unwrap();
+                            }
+                        }
+                        MenuEntry::Link(item_type, label, target) => {
+                            let tag = format!("[{}]", tag(*item_type));
+                            writeln!(out, "{:<6} {}  <{}>", tag, label, target).unwrap();
+                        }
+                    }
+                }
+                out
+            }
+            ResponseKind::TextFile(text) => text.clone(),
+            ResponseKind::BinaryFile(buf) => format!("Binary file, {} bytes\n", buf.len()),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use gopher::{ItemType, MenuEntry, Response, ResponseKind};
+use gtk::prelude::*;
+use gtk::{
+    Align, Box as GtkBox, Button, IconLookupFlags, IconTheme, Label, Orientation, StateFlags,
+    TextTag, TextView, TextWindowType,
+};
+use relm::EventStream;
+use url::Url;
+
+use crate::render::Renderer;
+use crate::window::Msg as WindowMsg;
+
+/// Size of the icons in front of menu lines, in pixels.
+const ICON_SIZE: i32 = 16;
+
+/// A line of a menu as it is shown, one per line of the text view.
+#[derive(Clone)]
+pub struct MenuRow {
+    /// The type of a link; `None` for information.
+    pub item_type: Option<ItemType>,
+    pub text: String,
+    pub url: Option<Url>,
+}
+
+/// The lines a menu is shown as. Links with a bad URL are left out.
+pub fn menu_rows(entries: &[MenuEntry]) -> Vec<MenuRow> {
+    let mut rows = Vec::new();
+    for entry in entries {
+        match entry {
+            MenuEntry::Information(text) => rows.extend(text.lines().map(|line| MenuRow {
+                item_type: None,
+                text: line.to_owned(),
+                url: None,
+            })),
+            MenuEntry::Link(item_type, label, target) => match Url::parse(target) {
+                Ok(url) => rows.push(MenuRow {
+                    item_type: Some(*item_type),
+                    text: label.clone(),
+                    url: Some(url),
+                }),
+                Err(err) => error!("Error parsing URL {}: {}", target, err),
+            },
+        }
+    }
+    rows
+}
+
+/// Builds the widgets for a page in the browser window.
+///
+/// Links for which `is_available` returns false are marked as unavailable, which is used to
+/// show what can't be opened in offline mode.
+pub struct GtkRenderer<'a> {
+    pub stream: EventStream<WindowMsg>,
+    pub is_available: &'a dyn Fn(&Url) -> bool,
+}
+
+impl<'a> Renderer for GtkRenderer<'a> {
+    type Output = GtkBox;
+
+    fn render(&self, response: &Response) -> GtkBox {
+        match &response.kind {
+            ResponseKind::Menu(entries) => {
+                menu_page(&entries, self.stream.clone(), self.is_available)
+            }
+            ResponseKind::TextFile(contents) => text_page(contents),
+            ResponseKind::BinaryFile(buf) => {
+                binary_page(&response.url, buf.len(), self.stream.clone())
+            }
+        }
+    }
+}
+
+/// Binary files can't be shown, so their page only has their size and a button to save them.
+fn binary_page(url: &Url, size: usize, stream: EventStream<WindowMsg>) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 10);
+    container.set_border_width(10);
+    let label = Label::new(format!("Binary file, {} bytes", size).as_str());
+    label.set_halign(Align::Start);
+    container.add(&label);
+
+    let save_button = Button::new_with_label("Save as...");
+    save_button.set_halign(Align::Start);
+    let url = url.clone();
+    save_button.connect_clicked(move |_| stream.emit(WindowMsg::SavePage(url.clone())));
+    container.add(&save_button);
+    container
+}
+
+fn text_page(contents: impl AsRef<str>) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 0);
+
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_cursor_visible(false);
+    text_view.set_property_monospace(true);
+    text_view
+        .get_buffer()
+        .map(|buffer| buffer.set_text(contents.as_ref()));
+
+    container.add(&text_view);
+    container
+}
+
+/// Renders a whole menu into one text view, so that large menus are quick to show and can be
+/// selected as a whole.
+///
+/// Every line starts with the number of its link, for picking it from the keyboard, and an icon
+/// for its type. Information lines get blanks instead, so that the columns of the text line up
+/// as they do in the menu.
+fn menu_page(
+    entries: &[MenuEntry],
+    stream: EventStream<WindowMsg>,
+    is_available: &dyn Fn(&Url) -> bool,
+) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 0);
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_cursor_visible(false);
+    text_view.set_property_monospace(true);
+    container.add(&text_view);
+    let buffer = match text_view.get_buffer() {
+        Some(buffer) => buffer,
+        None => return container,
+    };
+
+    let link_color = text_view.get_style_context().get_color(StateFlags::LINK);
+    let link_tag = TextTag::new("link");
+    link_tag.set_property_foreground_rgba(Some(&link_color));
+    link_tag.set_property_underline(pango::Underline::Single);
+    let number_color = text_view.get_style_context().get_color(StateFlags::NORMAL);
+    let number_tag = TextTag::new("link-number");
+    number_tag.set_property_foreground_rgba(Some(&gdk::RGBA {
+        alpha: 0.5,
+        ..number_color
+    }));
+    let unavailable_tag = TextTag::new("unavailable");
+    unavailable_tag.set_property_foreground_rgba(Some(&gdk::RGBA {
+        alpha: 0.5,
+        ..link_color
+    }));
+    if let Some(tag_table) = buffer.get_tag_table() {
+        tag_table.add(&link_tag);
+        tag_table.add(&number_tag);
+        tag_table.add(&unavailable_tag);
+    }
+
+    let theme = IconTheme::get_default();
+    let mut icons = HashMap::new();
+    let mut icon = |name: &'static str| {
+        icons
+            .entry(name)
+            .or_insert_with(|| {
+                let theme = theme.as_ref()?;
+                match theme.load_icon(name, ICON_SIZE, IconLookupFlags::FORCE_SIZE) {
+                    Ok(icon) => icon,
+                    Err(err) => {
+                        warn!("Could not load the icon {}: {}", name, err);
+                        None
+                    }
+                }
+            })
+            .clone()
+    };
+    let blank = icon("text-x-generic")
+        .and_then(|icon| icon.copy())
+        .map(|blank| {
+            blank.fill(0);
+            blank
+        });
+    let offline = icon("network-offline");
+
+    let rows = menu_rows(entries);
+    let width = rows
+        .iter()
+        .filter(|row| row.url.is_some())
+        .count()
+        .to_string()
+        .len();
+    let mut number = 0;
+    let mut iter = buffer.get_end_iter();
+    for row in &rows {
+        let (item_type, url) = match (row.item_type, &row.url) {
+            (Some(item_type), Some(url)) => (item_type, url),
+            _ => {
+                buffer.insert(&mut iter, &" ".repeat(width + 1));
+                if let Some(blank) = &blank {
+                    buffer.insert_pixbuf(&mut iter, blank);
+                    buffer.insert(&mut iter, " ");
+                }
+                buffer.insert(&mut iter, &row.text);
+                buffer.insert(&mut iter, "\n");
+                continue;
+            }
+        };
+
+        number += 1;
+        let label = format!("{:>width$} ", number, width = width);
+        buffer.insert_with_tags(&mut iter, &label, &[&number_tag]);
+        if let Some(icon) = icon(icon_name(item_type)) {
+            buffer.insert_pixbuf(&mut iter, &icon);
+            buffer.insert(&mut iter, " ");
+        }
+        if is_available(url) {
+            buffer.insert_with_tags(&mut iter, &row.text, &[&link_tag]);
+        } else {
+            buffer.insert_with_tags(&mut iter, &row.text, &[&link_tag, &unavailable_tag]);
+            if let Some(offline) = &offline {
+                buffer.insert(&mut iter, " ");
+                buffer.insert_pixbuf(&mut iter, offline);
+            }
+        }
+        buffer.insert(&mut iter, "\n");
+    }
+
+    // the link on each line of the buffer, if there is one
+    let links = rows
+        .into_iter()
+        .map(|row| Some(row).filter(|row| row.item_type.is_some() && row.url.is_some()))
+        .collect::<Vec<_>>();
+
+    let links = Rc::new(links);
+    let menu_links = links.clone();
+    let menu_stream = stream.clone();
+    // links get their own menu, anywhere else the text view's menu is shown
+    text_view.connect_button_press_event(move |view, event| {
+        if event.get_button() != 3 {
+            return Inhibit(false);
+        }
+        let link = link_line(view, event.get_position(), &menu_links)
+            .and_then(|line| menu_links[line].clone());
+        match link {
+            Some(link) => {
+                menu_stream.emit(WindowMsg::LinkMenu(link, 3, event.get_time()));
+                Inhibit(true)
+            }
+            None => Inhibit(false),
+        }
+    });
+
+    let click_links = links.clone();
+    let click_stream = stream.clone();
+    text_view.connect_button_release_event(move |view, event| {
+        // don't follow a link when a selection was made by dragging from it
+        let selected = view
+            .get_buffer()
+            .map(|buffer| buffer.get_has_selection())
+            .unwrap_or(false);
+        if event.get_button() == 1 && !selected {
+            if let Some(line) = link_line(view, event.get_position(), &click_links) {
+                if let Some(url) = click_links[line].as_ref().and_then(|row| row.url.clone()) {
+                    click_stream.emit(WindowMsg::OpenUrl(url));
+                }
+            }
+        }
+        Inhibit(false)
+    });
+
+    // the line of the link under the pointer, whose target is shown in the status bar
+    let hovered = Rc::new(Cell::new(None));
+    let motion_hovered = hovered.clone();
+    let motion_stream = stream.clone();
+    text_view.connect_motion_notify_event(move |view, event| {
+        let line = link_line(view, event.get_position(), &links);
+        let previous = motion_hovered.replace(line);
+        if line == previous {
+            return Inhibit(false);
+        }
+        if line.is_some() != previous.is_some() {
+            if let Some(window) = view.get_window(TextWindowType::Text) {
+                let name = if line.is_some() { "pointer" } else { "text" };
+                let cursor = gdk::Cursor::new_from_name(&window.get_display(), name);
+                window.set_cursor(cursor.as_ref());
+            }
+        }
+        let link = line
+            .and_then(|line| links[line].as_ref())
+            .and_then(|row| Some((row.item_type?, row.url.clone()?)));
+        motion_stream.emit(WindowMsg::HoverLink(link));
+        Inhibit(false)
+    });
+    text_view.connect_leave_notify_event(move |_, _| {
+        if hovered.replace(None).is_some() {
+            stream.emit(WindowMsg::HoverLink(None));
+        }
+        Inhibit(false)
+    });
+    container
+}
+
+/// The line of the link under a point given in the coordinates of the view.
+fn link_line(view: &TextView, (x, y): (f64, f64), links: &[Option<MenuRow>]) -> Option<usize> {
+    let (x, y) = view.window_to_buffer_coords(TextWindowType::Widget, x as i32, y as i32);
+    let iter = view.get_iter_at_location(x, y)?;
+    // points to the right of a line are at its end, which isn't part of the link
+    if iter.ends_line() {
+        return None;
+    }
+    let line = iter.get_line() as usize;
+    links.get(line)?.as_ref().map(|_| line)
+}
+
+fn icon_name(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::Dir => "folder",
+        ItemType::IndexServer => "system-search",
+        ItemType::Error => "dialog-error",
+        ItemType::Telnet | ItemType::Tn3270 => "utilities-terminal",
+        ItemType::CsoServer => "x-office-address-book",
+        ItemType::Gif | ItemType::Image => "image-x-generic",
+        ItemType::BinHex | ItemType::Dos | ItemType::Uuencoded | ItemType::Binary => {
+            "package-x-generic"
+        }
+        ItemType::Other(b'h') => "text-html",
+        ItemType::Other(b's') => "audio-x-generic",
+        _ => "text-x-generic",
+    }
+}
@@ -0,0 +1,418 @@
+use std::fs;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use encoding_rs::Encoding;
+use futures::{future, future::Either, Future, Sink, Stream};
+use gopher::gophermap::{self, selector_url};
+use gopher::{
+    decode_text, ItemType, MenuEntry, Request, RequestCodec, Response, ResponseCodec, ResponseKind,
+};
+use tokio::codec::{FramedRead, FramedWrite};
+use tokio::io::AsyncRead;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::runtime::Runtime;
+use tokio::timer::Timeout;
+use url::Url;
+
+use crate::args::ServeArgs;
+use crate::config::Config;
+use crate::errors::Error;
+use crate::local::{self, GOPHERMAP};
+
+/// How long a client has to send its selector.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Runs `gopher-browser serve` until it fails, returning the exit code.
+///
+/// Directories are served through their gophermap if they have one, and as a listing of their
+/// files otherwise. Searching a directory lists the files below it with the query in their name.
+pub fn run(args: &ServeArgs, config: &Config) -> i32 {
+    let root = match args.dir.canonicalize() {
+        Ok(root) if root.is_dir() => root,
+        Ok(root) => {
+            eprintln!("{} is not a directory", root.display());
+            return 1;
+        }
+        Err(err) => {
+            eprintln!("Could not open {}: {}", args.dir.display(), err);
+            return 1;
+        }
+    };
+    let addr = match (args.host.as_str(), args.port).to_socket_addrs() {
+        Ok(mut addrs) => match addrs.next() {
+            Some(addr) => addr,
+            None => {
+                eprintln!("Could not find the address of {}", args.host);
+                return 1;
+            }
+        },
+        Err(err) => {
+            eprintln!("Could not find the address of {}: {}", args.host, err);
+            return 1;
+        }
+    };
+    let listener = match TcpListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Could not listen on {}: {}", addr, err);
+            return 1;
+        }
+    };
+
+    eprintln!(
+        "Serving {} at gopher://{}:{}/",
+        root.display(),
+        args.host,
+        args.port
+    );
+    let server = Arc::new(Server {
+        root,
+        host: args.host.clone(),
+        port: args.port,
+        encodings: config.encodings(),
+    });
+    let accept = listener.incoming().for_each(move |stream| {
+        tokio::spawn(server.clone().handle(stream));
+        Ok(())
+    });
+
+    let mut runtime = Runtime::new().expect("failed to create runtime");
+    match runtime.block_on(accept) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("Error accepting connections: {}", err);
+            1
+        }
+    }
+}
+
+struct Server {
+    root: PathBuf,
+    host: String,
+    port: u16,
+    encodings: Vec<&'static Encoding>,
+}
+
+impl Server {
+    /// Answers the one request a client makes on a connection.
+    fn handle(self: Arc<Self>, stream: TcpStream) -> impl Future<Item = (), Error = ()> {
+        let peer = stream
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        let (reader, writer) = stream.split();
+
+        let codec = RequestCodec::server(self.host.clone(), self.port);
+        let read = FramedRead::new(reader, codec)
+            .into_future()
+            .map_err(|(err, _)| Error::from(err));
+        Timeout::new(read, REQUEST_TIMEOUT)
+            .map_err(|err| {
+                if err.is_elapsed() {
+                    Error::IO(io::Error::new(io::ErrorKind::TimedOut, "no request"))
+                } else if err.is_inner() {
+                    err.into_inner().unwrap()
+                } else {
+                    let timer = err.into_timer().unwrap();
+                    Error::IO(io::Error::other(timer))
+                }
+            })
+            .and_then(move |(request, _)| {
+                let request = match request {
+                    Some(request) => request,
+                    None => return Either::A(future::ok(())),
+                };
+                info!("{} {:?} {:?}", peer, request.selector(), request.query());
+                let response = self.respond(&request);
+                let write = FramedWrite::new(writer, ResponseCodec::new())
+                    .send(response)
+                    .map(|_| ())
+                    .map_err(Error::from);
+                Either::B(write)
+            })
+            .map_err(|err| warn!("Error serving a request: {}", err))
+    }
+
+    fn respond(&self, request: &Request) -> Response {
+        let selector = request.selector();
+        let path = match self.path(&selector) {
+            Some(path) => path,
+            None => return self.error(&selector, "Not found"),
+        };
+        let result = match request.query() {
+            Some(query) if path.is_dir() => Ok(self.search(&path, &query)),
+            _ if path.is_dir() => self.dir(&path),
+            _ => self.file(&path),
+        };
+        result.unwrap_or_else(|err| {
+            warn!("Error serving {}: {}", path.display(), err);
+            self.error(&selector, "Not found")
+        })
+    }
+
+    /// The file a selector names; hidden files and anything outside the root aren't served,
+    /// including files that symlinks in the root point to.
+    fn path(&self, selector: &str) -> Option<PathBuf> {
+        let relative = Path::new(selector.trim_start_matches('/'));
+        for component in relative.components() {
+            match component {
+                Component::Normal(name) if !name.to_string_lossy().starts_with('.') => (),
+                _ => return None,
+            }
+        }
+        let path = self.root.join(relative);
+        if self.is_inside(&path) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a path is in the root once symlinks are followed. The root is canonical, so this
+    /// is false for paths that don't exist too.
+    fn is_inside(&self, path: &Path) -> bool {
+        path.canonicalize()
+            .map(|target| target.starts_with(&self.root))
+            .unwrap_or(false)
+    }
+
+    /// The selector for a file under the root.
+    fn selector(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let parts = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        format!("/{}", parts.join("/"))
+    }
+
+    fn url(&self, item_type: ItemType, selector: &str) -> Url {
+        selector_url(&self.host, self.port, item_type, selector)
+            .unwrap_or_else(|| selector_url(&self.host, self.port, item_type, "").unwrap())
+    }
+
+    fn link(&self, path: &Path, display: String) -> MenuEntry {
+        let item_type = local::item_type(path);
+        let url = self.url(item_type, &self.selector(path));
+        MenuEntry::Link(item_type, display, url.to_string())
+    }
+
+    fn menu(&self, path: &Path, item_type: ItemType, entries: Vec<MenuEntry>) -> Response {
+        Response {
+            url: self.url(item_type, &self.selector(path)),
+            item_type,
+            kind: ResponseKind::Menu(entries),
+        }
+    }
+
+    fn error(&self, selector: &str, message: &str) -> Response {
+        let url = self.url(ItemType::Error, selector);
+        let entries = vec![MenuEntry::Link(
+            ItemType::Error,
+            message.to_owned(),
+            url.to_string(),
+        )];
+        Response {
+            url,
+            item_type: ItemType::Dir,
+            kind: ResponseKind::Menu(entries),
+        }
+    }
+
+    /// Files are sent as they are, whatever their encoding.
+    fn file(&self, path: &Path) -> Result<Response, Error> {
+        let item_type = local::item_type(path);
+        Ok(Response {
+            url: self.url(item_type, &self.selector(path)),
+            item_type,
+            kind: ResponseKind::BinaryFile(fs::read(path)?),
+        })
+    }
+
+    fn dir(&self, path: &Path) -> Result<Response, Error> {
+        let map_path = path.join(GOPHERMAP);
+        if map_path.exists() {
+            return self.gophermap(path, &map_path);
+        }
+
+        let mut children = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|child| !is_hidden(child) && self.is_inside(child))
+            .collect::<Vec<_>>();
+        children.sort();
+
+        let search = self.url(ItemType::IndexServer, &self.selector(path));
+        let mut entries = vec![MenuEntry::Link(
+            ItemType::IndexServer,
+            "Search files".to_owned(),
+            search.to_string(),
+        )];
+        for child in children {
+            let name = child
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            entries.push(self.link(&child, name));
+        }
+        Ok(self.menu(path, ItemType::Dir, entries))
+    }
+
+    /// Serves a gophermap, with selectors without a host on this server: absolute ones start at
+    /// the root, relative ones at the directory of the gophermap.
+    fn gophermap(&self, dir: &Path, map_path: &Path) -> Result<Response, Error> {
+        let text = decode_text(&fs::read(map_path)?, &self.encodings);
+        let dir_selector = self.selector(dir);
+
+        let mut entries = Vec::new();
+        let mut current = Vec::new();
+        for mut line in gophermap::parse(&text) {
+            // other unknown types, like "h", are still links for the client to make sense of
+            if line.item_type == ItemType::Other(b'i') {
+                current.push(line.display);
+                continue;
+            } else if !current.is_empty() {
+                entries.push(MenuEntry::Information(current.join("\n")));
+                current.clear();
+            }
+
+            if line.host.is_none() {
+                if !line.selector.starts_with('/') && !line.selector.starts_with("URL:") {
+                    line.selector =
+                        format!("{}/{}", dir_selector.trim_end_matches('/'), line.selector);
+                }
+                line.host = Some(self.host.clone());
+                line.port = Some(self.port);
+            }
+            if let Some(url) = line.url() {
+                entries.push(MenuEntry::Link(
+                    line.item_type,
+                    line.display,
+                    url.to_string(),
+                ));
+            }
+        }
+        if !current.is_empty() {
+            entries.push(MenuEntry::Information(current.join("\n")));
+        }
+        Ok(self.menu(dir, ItemType::Dir, entries))
+    }
+
+    /// Lists the files below `dir` with `query` in their name, ignoring case. Symlinks to
+    /// directories aren't searched, so that links back up the tree don't send it round in circles.
+    fn search(&self, dir: &Path, query: &str) -> Response {
+        let lowercase = query.to_lowercase();
+        let mut found = Vec::new();
+        let mut queue = vec![dir.to_path_buf()];
+        while let Some(next) = queue.pop() {
+            let children = match fs::read_dir(&next) {
+                Ok(children) => children,
+                Err(err) => {
+                    warn!("Could not read {}: {}", next.display(), err);
+                    continue;
+                }
+            };
+            for entry in children.filter_map(|entry| entry.ok()) {
+                let child = entry.path();
+                if is_hidden(&child) || !self.is_inside(&child) {
+                    continue;
+                }
+                let name = child
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                if name.contains(&lowercase) && name != GOPHERMAP {
+                    found.push(child.clone());
+                }
+                // the entry's own type, which is a symlink rather than what it points to
+                if entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false) {
+                    queue.push(child);
+                }
+            }
+        }
+        found.sort();
+
+        let mut entries = vec![MenuEntry::Information(format!(
+            "{} results for \"{}\"",
+            found.len(),
+            query
+        ))];
+        for path in found {
+            let display = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .into_owned();
+            entries.push(self.link(&path, display));
+        }
+        self.menu(dir, ItemType::IndexServer, entries)
+    }
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy().starts_with('.'))
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn doesnt_follow_symlinks_out_of_the_root() {
+        use std::os::unix::fs::symlink;
+
+        let dir = std::env::temp_dir().join(format!("gopher-serve-test-{}", std::process::id()));
+        let root = dir.join("root");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/readme.txt"), "hello").unwrap();
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        symlink(dir.join("secret.txt"), root.join("secret.txt")).unwrap();
+        symlink(root.join("docs"), root.join("links")).unwrap();
+
+        let server = Server {
+            root: root.canonicalize().unwrap(),
+            host: "localhost".to_owned(),
+            port: 70,
+            encodings: Vec::new(),
+        };
+        assert!(server.path("/docs/readme.txt").is_some());
+        assert!(server.path("/links/readme.txt").is_some());
+        assert_eq!(server.path("/secret.txt"), None);
+        assert_eq!(server.path("/missing.txt"), None);
+        assert_eq!(server.path("/../secret.txt"), None);
+
+        // listings and searches leave out what's outside too, and don't go round in loops
+        symlink(&dir, root.join("outside")).unwrap();
+        symlink(&root, root.join("docs/up")).unwrap();
+        symlink(".", root.join("docs/here")).unwrap();
+        let links = |response: Response| match response.kind {
+            ResponseKind::Menu(entries) => entries
+                .into_iter()
+                .filter_map(|entry| match entry {
+                    MenuEntry::Link(ItemType::IndexServer, ..) => None,
+                    MenuEntry::Link(_, display, _) => Some(display),
+                    MenuEntry::Information(_) => None,
+                })
+                .collect::<Vec<_>>(),
+            kind => panic!("not a menu: {:?}", kind),
+        };
+        assert_eq!(
+            links(server.dir(&server.root).unwrap()),
+            vec!["docs", "links"]
+        );
+        assert_eq!(
+            links(server.search(&server.root, "readme")),
+            vec!["docs/readme.txt"]
+        );
+        assert!(links(server.search(&server.root, "secret")).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+use std::str;
+
+/// Responses longer than this are cut off when their source is shown, since every byte can
+/// take several characters to show.
+pub const MAX_SHOWN: usize = 512 * 1024;
+
+/// How a piece of a line of source is shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Text,
+    /// A symbol for a tab or a line ending.
+    Control,
+    /// Escapes for bytes that aren't valid UTF-8.
+    Invalid,
+}
+
+/// A line of a response as it is shown in its source.
+#[derive(Debug)]
+pub struct SourceLine {
+    pub spans: Vec<(Kind, String)>,
+    /// Whether the line is left out of the menu as a bad line.
+    pub rejected: bool,
+}
+
+/// Splits a response into lines for showing its source: tabs and line endings are shown as
+/// symbols, and bytes that aren't UTF-8 as escapes like `\xff`. If the response is a menu, the
+/// lines the menu parser leaves out are marked.
+pub fn lines(body: &[u8], is_menu: bool) -> Vec<SourceLine> {
+    let mut lines = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        let end = rest
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(rest.len(), |newline| newline + 1);
+        let (line, after) = rest.split_at(end);
+        lines.push(source_line(line, is_menu));
+        rest = after;
+    }
+    lines
+}
+
+fn source_line(line: &[u8], is_menu: bool) -> SourceLine {
+    let (content, ending) = if line.ends_with(b"\r\n") {
+        (&line[..line.len() - 2], "\u{240d}\u{240a}")
+    } else if line.ends_with(b"\n") {
+        (&line[..line.len() - 1], "\u{240a}")
+    } else {
+        (line, "")
+    };
+
+    let mut spans = Vec::new();
+    let mut rest = content;
+    loop {
+        match str::from_utf8(rest) {
+            Ok(text) => {
+                push_text(&mut spans, text);
+                break;
+            }
+            Err(err) => {
+                let (valid, after) = rest.split_at(err.valid_up_to());
+                // the part up to the error is valid, so this can't fail
+                push_text(&mut spans, str::from_utf8(valid).unwrap_or(""));
+                let bad = err.error_len().unwrap_or_else(|| after.len());
+                let escaped = after[..bad]
+                    .iter()
+                    .map(|byte| format!("\\x{:02x}", byte))
+                    .collect::<String>();
+                push(&mut spans, Kind::Invalid, &escaped);
+                rest = &after[bad..];
+            }
+        }
+    }
+    push(&mut spans, Kind::Control, ending);
+
+    let rejected = is_menu && gopher::is_bad_line(&String::from_utf8_lossy(content));
+    SourceLine { spans, rejected }
+}
+
+/// Adds text to a line, with symbols for tabs and stray carriage returns. Tabs are kept after
+/// their symbol so that columns still line up.
+fn push_text(spans: &mut Vec<(Kind, String)>, text: &str) {
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        let symbol = match c {
+            '\t' => "\u{2192}\t",
+            '\r' => "\u{240d}",
+            _ => continue,
+        };
+        push(spans, Kind::Text, &text[start..i]);
+        push(spans, Kind::Control, symbol);
+        start = i + c.len_utf8();
+    }
+    push(spans, Kind::Text, &text[start..]);
+}
+
+/// Adds a piece to a line, joining it to the last one if they're shown the same way.
+fn push(spans: &mut Vec<(Kind, String)>, kind: Kind, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some((last, last_text)) if *last == kind => last_text.push_str(text),
+        _ => spans.push((kind, text.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(line: &SourceLine) -> Vec<(Kind, &str)> {
+        line.spans
+            .iter()
+            .map(|(kind, text)| (*kind, text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn escapes_invalid_utf8() {
+        let lines = lines(b"caf\xc3\xa9 \xff\xfeok\nab\xe2\x82", false);
+        assert_eq!(
+            spans(&lines[0]),
+            vec![
+                (Kind::Text, "caf\u{e9} "),
+                (Kind::Invalid, "\\xff\\xfe"),
+                (Kind::Text, "ok"),
+                (Kind::Control, "\u{240a}"),
+            ]
+        );
+        // a character cut off at the end of the response
+        assert_eq!(
+            spans(&lines[1]),
+            vec![(Kind::Text, "ab"), (Kind::Invalid, "\\xe2\\x82")]
+        );
+    }
+
+    #[test]
+    fn shows_line_endings_and_tabs() {
+        let lines = lines(b"one\r\ntwo\nthree\rfour\tfive", false);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            spans(&lines[0]),
+            vec![(Kind::Text, "one"), (Kind::Control, "\u{240d}\u{240a}")]
+        );
+        assert_eq!(
+            spans(&lines[1]),
+            vec![(Kind::Text, "two"), (Kind::Control, "\u{240a}")]
+        );
+        assert_eq!(
+            spans(&lines[2]),
+            vec![
+                (Kind::Text, "three"),
+                (Kind::Control, "\u{240d}"),
+                (Kind::Text, "four"),
+                (Kind::Control, "\u{2192}\t"),
+                (Kind::Text, "five"),
+            ]
+        );
+    }
+
+    #[test]
+    fn marks_lines_left_out_of_menus() {
+        let body = b"iHello\tfake\t(NULL)\t0\r\njust some text\r\n\r\n.\r\n";
+        let rejected = |is_menu| {
+            lines(body, is_menu)
+                .iter()
+                .map(|line| line.rejected)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rejected(true), vec![false, true, false, false]);
+        assert_eq!(rejected(false), vec![false; 4]);
+    }
+}
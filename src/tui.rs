@@ -0,0 +1,528 @@
+//! A front-end for the terminal, for machines without a display.
+//!
+//! It sends the same events to the event loop as the window does, and gets its replies through
+//! a plain channel instead of a relm one.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use futures::sync::{mpsc::UnboundedSender, oneshot::Sender as OneshotSender};
+use gopher::{search_url, ItemType, MenuEntry, Request, Response, ResponseKind};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+use termion::{clear, cursor, style, terminal_size};
+use url::Url;
+
+use crate::about;
+use crate::args::parse_url;
+use crate::bookmarks::Bookmarks;
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::errors::Error;
+use crate::events::{Event, Reply, Sender as ReplySender};
+use crate::history::History;
+use crate::local;
+use crate::render::{self, Renderer};
+
+const HELP: &str = "0-9 link  o go to  h back  l forward  r reload  s save  j/k scroll  q quit";
+
+pub struct Params {
+    pub stop_tx: OneshotSender<()>,
+    pub evl_tx: UnboundedSender<Event>,
+    pub cache: Arc<Mutex<Cache>>,
+    pub config: Arc<Mutex<Config>>,
+    pub config_error: Option<Error>,
+    pub bookmarks_path: Option<PathBuf>,
+    pub history_path: Option<PathBuf>,
+    pub urls: Vec<Url>,
+}
+
+/// Runs the terminal front-end until the user quits.
+pub fn run(params: Params) {
+    let stdout = match io::stdout().into_raw_mode() {
+        Ok(stdout) => stdout,
+        Err(err) => {
+            eprintln!("Could not set up the terminal: {}", err);
+            let _ = params.stop_tx.send(());
+            return;
+        }
+    };
+    let mut screen = AlternateScreen::from(stdout);
+
+    // keys and replies from the event loop end up in the same queue
+    let (input_tx, input_rx) = mpsc::channel();
+    let (reply_tx, reply_rx) = mpsc::channel();
+    let reply_input_tx = input_tx.clone();
+    thread::spawn(move || {
+        for reply in reply_rx {
+            if reply_input_tx.send(Input::Reply(reply)).is_err() {
+                break;
+            }
+        }
+    });
+    thread::spawn(move || {
+        for key in io::stdin().keys() {
+            match key {
+                Ok(key) => {
+                    if input_tx.send(Input::Key(key)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    error!("Error reading from the terminal: {}", err);
+                    break;
+                }
+            }
+        }
+    });
+
+    let default_port = params.config.lock().unwrap().default_port;
+    let mut tui = Tui {
+        evl_tx: params.evl_tx,
+        sender: ReplySender::Terminal(reply_tx),
+        cache: params.cache,
+        config: params.config,
+        bookmarks: Bookmarks::load(params.bookmarks_path, default_port),
+        history: History::load(params.history_path),
+        page: None,
+        scroll: 0,
+        back: Vec::new(),
+        forward: Vec::new(),
+        loading: None,
+        prompt: None,
+        status: HELP.to_owned(),
+    };
+    tui.open_urls(params.urls);
+    if let Some(err) = params.config_error {
+        tui.fail(err);
+    }
+
+    if let Err(err) = tui.draw(&mut screen) {
+        error!("Error drawing the screen: {}", err);
+    }
+    for input in input_rx {
+        match input {
+            Input::Key(key) => {
+                if !tui.key(key) {
+                    break;
+                }
+            }
+            Input::Reply(reply) => tui.reply(reply),
+        }
+        if let Err(err) = tui.draw(&mut screen) {
+            error!("Error drawing the screen: {}", err);
+            break;
+        }
+    }
+
+    let _ = write!(screen, "{}", cursor::Show);
+    drop(screen);
+    let _ = params.stop_tx.send(());
+}
+
+enum Input {
+    Key(Key),
+    Reply(Reply),
+}
+
+/// How the page being loaded was reached, which decides what happens to back and forward.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Navigation {
+    Visit,
+    Back,
+    Forward,
+    Reload,
+}
+
+/// What is being typed into the status line.
+enum Prompt {
+    Link(String),
+    Go(String),
+    Search(Url, String),
+}
+
+/// A page as it's shown in the terminal, with its links numbered in order.
+pub struct Page {
+    pub url: Url,
+    pub lines: Vec<PageLine>,
+    pub links: Vec<(ItemType, Url)>,
+    /// The contents of a binary file, which can only be saved.
+    pub download: Option<Vec<u8>>,
+}
+
+pub enum PageLine {
+    Text(String),
+    /// The index of a link in `links`, and its label.
+    Link(usize, String),
+}
+
+/// Renders menus and text files into lines for the terminal.
+pub struct PageRenderer;
+
+impl Renderer for PageRenderer {
+    type Output = Page;
+
+    fn render(&self, response: &Response) -> Page {
+        let mut lines = Vec::new();
+        let mut links = Vec::new();
+        let mut download = None;
+        match &response.kind {
+            ResponseKind::Menu(entries) => {
+                for entry in entries {
+                    match entry {
+                        MenuEntry::Information(text) => {
+                            lines.extend(text.lines().map(|line| PageLine::Text(expand(line))));
+                        }
+                        MenuEntry::Link(item_type, label, target) => match Url::parse(target) {
+                            Ok(url) => {
+                                lines.push(PageLine::Link(links.len(), expand(label)));
+                                links.push((*item_type, url));
+                            }
+                            Err(err) => error!("Error parsing URL {}: {}", target, err),
+                        },
+                    }
+                }
+            }
+            ResponseKind::TextFile(text) => {
+                lines.extend(text.lines().map(|line| PageLine::Text(expand(line))));
+            }
+            ResponseKind::BinaryFile(buf) => {
+                lines.push(PageLine::Text(format!(
+                    "Binary file, {} bytes. Press s to save it.",
+                    buf.len()
+                )));
+                download = Some(buf.clone());
+            }
+        }
+        Page {
+            url: response.url.clone(),
+            lines,
+            links,
+            download,
+        }
+    }
+}
+
+/// Replaces tabs, which would throw off the width of the line.
+fn expand(line: &str) -> String {
+    line.replace('\t', "    ")
+}
+
+struct Tui {
+    evl_tx: UnboundedSender<Event>,
+    sender: ReplySender,
+    cache: Arc<Mutex<Cache>>,
+    config: Arc<Mutex<Config>>,
+    bookmarks: Bookmarks,
+    history: History,
+    page: Option<Page>,
+    scroll: usize,
+    back: Vec<Url>,
+    forward: Vec<Url>,
+    loading: Option<(Navigation, Url)>,
+    prompt: Option<Prompt>,
+    status: String,
+}
+
+impl Tui {
+    /// Handles a key, returning false if the user wants to quit.
+    fn key(&mut self, key: Key) -> bool {
+        if let Some(prompt) = self.prompt.take() {
+            self.prompt_key(prompt, key);
+            return true;
+        }
+
+        let height = terminal_size()
+            .map(|(_, height)| height as usize)
+            .unwrap_or(24);
+        match key {
+            Key::Char('q') | Key::Ctrl('c') => return false,
+            Key::Char('j') | Key::Down => self.scroll_by(1),
+            Key::Char('k') | Key::Up => self.scroll_by(-1),
+            Key::Char(' ') | Key::PageDown => self.scroll_by(height as isize - 2),
+            Key::PageUp => self.scroll_by(2 - height as isize),
+            Key::Char('g') | Key::Home => self.scroll = 0,
+            Key::Char('G') | Key::End => self.scroll_by(isize::max_value()),
+            Key::Char('h') | Key::Left | Key::Backspace => match self.back.last().cloned() {
+                Some(url) => self.open(url, Navigation::Back),
+                None => self.status = "Nothing to go back to".to_owned(),
+            },
+            Key::Char('l') | Key::Right => match self.forward.last().cloned() {
+                Some(url) => self.open(url, Navigation::Forward),
+                None => self.status = "Nothing to go forward to".to_owned(),
+            },
+            Key::Char('r') => {
+                if let Some(url) = self.page.as_ref().map(|page| page.url.clone()) {
+                    // as in the window, keep the cached copy if it's the only one we'll get
+                    let mut cache = self.cache.lock().unwrap();
+                    if !cache.is_offline() {
+                        cache.invalidate(&url);
+                    }
+                    drop(cache);
+                    self.open(url, Navigation::Reload);
+                }
+            }
+            Key::Char('o') => {
+                let current = self.page.as_ref().map(|page| page.url.to_string());
+                self.prompt = Some(Prompt::Go(current.unwrap_or_default()));
+            }
+            Key::Char('s') => self.save(),
+            Key::Char(c) if c.is_ascii_digit() => self.prompt = Some(Prompt::Link(c.to_string())),
+            Key::Char('?') => self.status = HELP.to_owned(),
+            _ => (),
+        }
+        true
+    }
+
+    fn prompt_key(&mut self, mut prompt: Prompt, key: Key) {
+        let input = match &mut prompt {
+            Prompt::Link(input) | Prompt::Go(input) | Prompt::Search(_, input) => input,
+        };
+        match key {
+            Key::Esc | Key::Ctrl('c') => return,
+            Key::Backspace => {
+                input.pop();
+            }
+            Key::Char('\n') => return self.submit(prompt),
+            Key::Char(c) => input.push(c),
+            _ => (),
+        }
+        self.prompt = Some(prompt);
+    }
+
+    fn submit(&mut self, prompt: Prompt) {
+        match prompt {
+            Prompt::Link(input) => match input.parse::<usize>() {
+                Ok(n) if n > 0 => self.follow(n - 1),
+                _ => self.status = format!("No link {:?}", input),
+            },
+            Prompt::Go(input) => match parse_url(input.trim()) {
+                Ok(url) => self.open(url, Navigation::Visit),
+                Err(err) => self.status = err,
+            },
+            Prompt::Search(url, query) => self.open(search_url(&url, &query), Navigation::Visit),
+        }
+    }
+
+    /// Opens the link numbered `index`, asking for a query first if it's a search.
+    fn follow(&mut self, index: usize) {
+        let link = self
+            .page
+            .as_ref()
+            .and_then(|page| page.links.get(index))
+            .cloned();
+        match link {
+            Some((ItemType::IndexServer, url)) => {
+                self.prompt = Some(Prompt::Search(url, String::new()))
+            }
+            Some((_, url)) => self.open(url, Navigation::Visit),
+            None => self.status = format!("No link {}", index + 1),
+        }
+    }
+
+    /// Saves the binary file on the page to the download directory.
+    fn save(&mut self) {
+        let (url, buf) = match &self.page {
+            Some(Page {
+                url,
+                download: Some(buf),
+                ..
+            }) => (url, buf),
+            _ => {
+                self.status = "Only binary files can be saved".to_owned();
+                return;
+            }
+        };
+        let dir = self.config.lock().unwrap().download_dir();
+        self.status = match local::save_download(&dir, url, buf) {
+            Ok(path) => format!("Saved {} to {}", url, path.display()),
+            Err(err) => format!("Error: {}", err),
+        };
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        let len = self.page.as_ref().map(|page| page.lines.len()).unwrap_or(0);
+        let scroll = (self.scroll as isize).saturating_add(lines);
+        self.scroll = scroll.max(0).min(len.saturating_sub(1) as isize) as usize;
+    }
+
+    fn open_urls(&mut self, urls: Vec<Url>) {
+        let url = match urls.into_iter().next() {
+            Some(url) => url,
+            // the home page was validated when the config was loaded
+            None => Url::parse(&self.config.lock().unwrap().home_page).unwrap(),
+        };
+        self.open(url, Navigation::Visit);
+    }
+
+    fn open(&mut self, url: Url, navigation: Navigation) {
+        info!("Opening URL {:?}", url);
+        self.loading = Some((navigation, url.clone()));
+        self.status = format!("Loading {}", url);
+
+        if url.scheme() == "about" {
+            let page = about::page(
+                &url,
+                &self.cache.lock().unwrap(),
+                &self.bookmarks,
+                &self.history,
+            );
+            match page {
+                Some(response) => self.show(response, None),
+                None => self.fail(Error::InvalidUrl(url.to_string())),
+            }
+            return;
+        }
+        let (default_port, encodings) = {
+            let config = self.config.lock().unwrap();
+            (config.default_port, config.encodings())
+        };
+        if url.scheme() == "file" {
+            match local::open(&url, &encodings) {
+                Ok(response) => self.show(response, None),
+                Err(err) => self.fail(err),
+            }
+            return;
+        }
+
+        let request = match Request::with_default_port(url, default_port) {
+            Ok(request) => request,
+            Err(err) => return self.fail(err.into()),
+        };
+        let event = Event::MakeRequest(request, self.sender.clone());
+        if let Err(err) = self.evl_tx.unbounded_send(event) {
+            error!("Error sending request: {}", err);
+        }
+    }
+
+    fn reply(&mut self, reply: Reply) {
+        match reply {
+            Reply::Response(response, fetched) => self.show(response, fetched),
+            Reply::OpenUrls(urls) => self.open_urls(urls),
+            Reply::Fail(err) => self.fail(*err),
+            Reply::Progress(_, _)
+            | Reply::Exchange(_)
+            | Reply::MirrorProgress(_)
+            | Reply::LinkReports(_, _) => (),
+        }
+    }
+
+    fn show(&mut self, response: Response, fetched: Option<SystemTime>) {
+        // only show the page that was asked for last
+        let navigation = match &self.loading {
+            Some((navigation, url)) if *url == response.url => *navigation,
+            _ => return,
+        };
+        self.loading = None;
+
+        let current = self.page.as_ref().map(|page| page.url.clone());
+        match navigation {
+            Navigation::Visit => {
+                self.back.extend(current.filter(|url| *url != response.url));
+                self.forward.clear();
+            }
+            Navigation::Back => {
+                self.back.pop();
+                self.forward.extend(current);
+            }
+            Navigation::Forward => {
+                self.forward.pop();
+                self.back.extend(current);
+            }
+            Navigation::Reload => (),
+        }
+
+        if response.url.scheme() != "about" {
+            if let Err(err) = self.history.record(&response) {
+                error!("Could not record history: {}", err);
+            }
+        }
+
+        self.status = match fetched {
+            Some(_) => format!("{} (cached)", response.url),
+            None => response.url.to_string(),
+        };
+        if navigation != Navigation::Reload {
+            self.scroll = 0;
+        }
+        self.page = Some(PageRenderer.render(&response));
+        self.scroll_by(0);
+    }
+
+    fn fail(&mut self, err: Error) {
+        self.loading = None;
+        self.status = format!("Error: {}", err);
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let (width, height) = terminal_size()?;
+        let (width, rows) = (width as usize, height.saturating_sub(1) as usize);
+        write!(out, "{}{}", clear::All, cursor::Hide)?;
+
+        if let Some(page) = &self.page {
+            let visible = page.lines.iter().skip(self.scroll).take(rows);
+            for (row, line) in visible.enumerate() {
+                write!(out, "{}", cursor::Goto(1, row as u16 + 1))?;
+                match line {
+                    PageLine::Text(text) => write!(out, "{}", truncate(text, width))?,
+                    PageLine::Link(index, label) => {
+                        let item_type = page.links[*index].0;
+                        let tag = format!("[{}]", render::tag(item_type));
+                        let prefix = format!("{:>3} {:<6} ", index + 1, tag);
+                        let label = truncate(label, width.saturating_sub(prefix.len()));
+                        write!(
+                            out,
+                            "{:>3} {}{:<6}{} {}",
+                            index + 1,
+                            render::color(item_type),
+                            tag,
+                            style::Reset,
+                            label
+                        )?;
+                    }
+                }
+            }
+        }
+
+        // the status line shows what's being typed, or what's going on
+        let (text, position) = match &self.prompt {
+            Some(Prompt::Link(input)) => (format!("Link: {}", input), None),
+            Some(Prompt::Go(input)) => (format!("Go to: {}", input), None),
+            Some(Prompt::Search(_, input)) => (format!("Search: {}", input), None),
+            None => {
+                let position = self.page.as_ref().map(|page| {
+                    let last = (self.scroll + rows).min(page.lines.len());
+                    format!("{}-{}/{}", self.scroll + 1, last, page.lines.len())
+                });
+                (self.status.clone(), position)
+            }
+        };
+        let position = position.unwrap_or_default();
+        let text = truncate(&text, width.saturating_sub(position.len() + 1));
+        write!(
+            out,
+            "{}{}{:<width$}{}{}",
+            cursor::Goto(1, height),
+            style::Invert,
+            text,
+            position,
+            style::Reset,
+            width = width.saturating_sub(position.len())
+        )?;
+        if self.prompt.is_some() {
+            let column = text.chars().count() as u16 + 1;
+            write!(out, "{}{}", cursor::Goto(column, height), cursor::Show)?;
+        }
+        out.flush()
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
@@ -1,28 +1,163 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use futures::sync::{mpsc::UnboundedSender, oneshot::Sender as OneshotSender};
 use futures::Async;
+use gdk::enums::key;
+use gdk::ModifierType;
+use gio::prelude::*;
+use gio::{FileMonitor, FileMonitorEvent, FileMonitorFlags};
+use gopher::{ItemType, MenuEntry, Request, Response, ResponseKind};
 use gtk::prelude::*;
 use gtk::{
-    Application, ApplicationWindow, Box as GtkBox, Label, Notebook, Orientation, PackType,
-    ScrolledWindow, SearchEntry, WindowPosition, WindowType, NONE_ADJUSTMENT,
+    AccelFlags, AccelGroup, Align, Application, ApplicationWindow, Box as GtkBox, Button,
+    ButtonsType, CellRendererText, CheckButton, ComboBoxText, CssProvider, Dialog, DialogFlags,
+    Entry, EntryCompletion, EntryIconPosition, FileChooserAction, FileChooserButton,
+    FileChooserDialog, FontButton, Grid, HeaderBar, IconSize, Image, Label, ListStore, Menu,
+    MenuButton, MenuItem, MessageDialog, MessageType, Notebook, Orientation, PackType, Paned,
+    Popover, ProgressBar, ResponseType, ScrolledWindow, SearchBar, SearchEntry, SeparatorMenuItem,
+    SpinButton, Statusbar, StyleContext, TextBuffer, TextIter, TextTag, TextView, TextWindowType,
+    ToggleButton, TreeView, TreeViewColumn, WindowPosition, WindowType, NONE_ADJUSTMENT,
 };
 use relm::{Channel, Relm, Sender, Update, Widget};
 use url::Url;
 
+use crate::about;
+use crate::args::{self, Format};
+use crate::bookmarks::{self, Bookmark, Bookmarks};
+use crate::cache::Cache;
+use crate::check::{self, LinkReport, Options as CheckOptions};
+use crate::config::{self, Config, Keys};
+use crate::convert;
 use crate::errors::Error;
-use crate::events::{Event, Reply};
-use crate::gopher_async::{Request, Response};
+use crate::events::{Event, Reply, Sender as ReplySender};
+use crate::filter::Filter;
+use crate::find::{self, Query};
+use crate::history::History;
+use crate::inspector::{self, Exchange};
+use crate::instance;
+use crate::local;
+use crate::mirror::{self, Options as MirrorOptions, Progress as MirrorProgress};
+use crate::render::{menu_rows, GtkRenderer, MenuRow, Renderer};
+use crate::source::{self, SourceLine};
 
 pub struct Window {
     window: gtk::Window,
     notebook: gtk::Notebook,
+    bookmarks_menu: Menu,
+    // visited URLs, for address bar completion
+    history_store: ListStore,
+    pages: Vec<Page>,
+    mirror_views: HashMap<PathBuf, MirrorView>,
+    inspector: Inspector,
+    /// Shows where hovered links go and how requests are getting on.
+    statusbar: Statusbar,
+    /// When the requests still waiting for a response were sent.
+    loading: HashMap<Url, Instant>,
+    /// The tabs that requested pages are shown in, in place of the page there. Pages without
+    /// one get a new tab.
+    targets: HashMap<Url, GtkBox>,
+    /// Where to save requested links to instead of showing them.
+    save_targets: HashMap<Url, PathBuf>,
+    // font and colors from the config
+    css_provider: CssProvider,
     model: Model,
 }
 
+/// What is shown in a tab.
+struct Page {
+    root: GtkBox,
+    url: Url,
+    item_type: ItemType,
+    content: ScrolledWindow,
+    /// What was rendered into `content`, and the text view in it.
+    body: GtkBox,
+    text_view: Option<TextView>,
+    /// The lines of a menu, in the order they're shown in the text view.
+    rows: Vec<MenuRow>,
+    /// The row of the link highlighted from the keyboard.
+    cursor: Option<usize>,
+    /// Digits typed so far to pick a link by its number.
+    number: String,
+    /// Lint warnings, shown above local gophermaps.
+    warnings: GtkBox,
+    filter: FilterBar,
+    find: FindBar,
+    // reloads local gophermaps when they're saved
+    _monitor: Option<FileMonitor>,
+}
+
+/// The filter above a menu.
+struct FilterBar {
+    row: GtkBox,
+    entry: SearchEntry,
+    only_dirs: CheckButton,
+    only_downloads: CheckButton,
+    status: Label,
+}
+
+/// The find bar of a tab and what it found.
+struct FindBar {
+    bar: SearchBar,
+    entry: SearchEntry,
+    match_case: CheckButton,
+    regex: CheckButton,
+    status: Label,
+    /// Character offsets of the matches in the page.
+    matches: Vec<(usize, usize)>,
+    current: usize,
+}
+
+/// A small window showing the progress of saving a site.
+struct MirrorView {
+    window: gtk::Window,
+    label: Label,
+    progress_bar: ProgressBar,
+    open_button: Button,
+}
+
+/// The network inspector, below the tabs: every request made for a page, and the raw request and
+/// response of the selected one.
+struct Inspector {
+    panel: GtkBox,
+    store: ListStore,
+    count: Label,
+    detail: TextView,
+    exchanges: Vec<Exchange>,
+}
+
+/// What the window is started with.
+pub struct Params {
+    pub stop_tx: OneshotSender<()>,
+    pub evl_tx: UnboundedSender<Event>,
+    pub cache: Arc<Mutex<Cache>>,
+    pub config: Arc<Mutex<Config>>,
+    /// Where the preferences are saved to.
+    pub config_path: Option<PathBuf>,
+    /// Why the config file couldn't be used, to be shown once the window is up.
+    pub config_error: Option<Error>,
+    pub bookmarks_path: Option<PathBuf>,
+    pub history_path: Option<PathBuf>,
+    pub private: bool,
+    /// Socket to listen on for URLs from later invocations.
+    pub instance_socket: Option<PathBuf>,
+    /// URLs to open in tabs; the home page is opened if there are none.
+    pub urls: Vec<Url>,
+}
+
 pub struct Model {
     stop_tx: Option<OneshotSender<()>>,
     evl_tx: UnboundedSender<Event>,
+    cache: Arc<Mutex<Cache>>,
+    config: Arc<Mutex<Config>>,
+    config_path: Option<PathBuf>,
+    private: bool,
+    instance_socket: Option<PathBuf>,
+    bookmarks: Bookmarks,
+    history: History,
     channel: Channel<Reply>,
     sender: Arc<Mutex<Sender<Reply>>>,
     relm: Relm<Window>,
@@ -30,31 +165,106 @@ pub struct Model {
 
 #[derive(Msg)]
 pub enum Msg {
+    /// Opens a page in the current tab.
     OpenUrl(Url),
-    OpenedUrl(Response),
+    OpenInNewTab(Url),
+    Reload(Url),
+    SaveSite(Url),
+    OpenUrls(Vec<Url>),
+    BookmarkPage,
+    ShowPreferences,
+    MirrorProgress(MirrorProgress),
+    OpenedUrl(Response, Option<SystemTime>),
+    /// Bytes received so far for a request.
+    Progress(Url, usize),
+    Exchange(Box<Exchange>),
+    ShowInspector(bool),
+    /// Shows the raw request and response of an exchange in the inspector.
+    InspectExchange(usize),
+    ClearExchanges,
+    /// The link under the pointer, or `None` when it leaves links.
+    HoverLink(Option<(ItemType, Url)>),
+    /// Shows the menu for a right-clicked link, with the button and time of the click.
+    LinkMenu(MenuRow, u32, u32),
+    CopyUrl(Url),
+    /// Asks where to save a link, and fetches it.
+    SaveTarget(Url),
+    BookmarkLink(MenuRow),
+    ShowItemInfo(MenuRow),
+    ViewSource(Url),
+    SavePage(Url),
+    LocalChanged(Url),
+    CheckLinks(Url),
+    /// Shows the find bar of the current tab.
+    Find,
+    FindChanged,
+    /// Goes to the next match, or the previous one if true.
+    FindNext(bool),
+    FindClosed,
+    FilterChanged,
+    /// Highlights the next link, or a previous one if negative.
+    MoveCursor(i32),
+    /// Follows the link typed as a number, or else the highlighted one.
+    FollowLink,
+    TypeDigit(char),
+    /// Opens the menu one selector level up from the current page.
+    GoUp,
+    CheckedLinks(Url, Vec<LinkReport>),
+    SetOffline(bool),
     Fail(Error),
     Quit,
 }
 
 impl Update for Window {
     type Model = Model;
-    type ModelParam = (OneshotSender<()>, UnboundedSender<Event>);
+    type ModelParam = Params;
     type Msg = Msg;
 
-    fn model(relm: &Relm<Self>, (stop_tx, evl_tx): Self::ModelParam) -> Model {
+    fn model(relm: &Relm<Self>, params: Params) -> Model {
         let stream = relm.stream().clone();
-        stream.emit(Msg::OpenUrl(Url::parse("gopher://iptq.io").unwrap()));
+        stream.emit(Msg::OpenUrls(params.urls));
+        if let Some(err) = params.config_error {
+            stream.emit(Msg::Fail(err));
+        }
 
         let (channel, sender) = Channel::new(move |reply| {
             match reply {
-                Reply::Response(response) => stream.emit(Msg::OpenedUrl(response)),
+                Reply::Response(response, fetched) => {
+                    stream.emit(Msg::OpenedUrl(response, fetched))
+                }
+                Reply::Progress(url, bytes) => stream.emit(Msg::Progress(url, bytes)),
+                Reply::Exchange(exchange) => stream.emit(Msg::Exchange(exchange)),
+                Reply::MirrorProgress(progress) => stream.emit(Msg::MirrorProgress(progress)),
+                Reply::LinkReports(url, reports) => stream.emit(Msg::CheckedLinks(url, reports)),
+                Reply::OpenUrls(urls) => stream.emit(Msg::OpenUrls(urls)),
+                Reply::Fail(err) => stream.emit(Msg::Fail(*err)),
             };
         });
         let sender = Arc::new(Mutex::new(sender));
 
+        if let Some(socket) = &params.instance_socket {
+            let sender = sender.clone();
+            let listening = instance::listen(socket, move |urls| {
+                if let Err(err) = sender.lock().unwrap().send(Reply::OpenUrls(urls)) {
+                    error!("Error sending URLs to the window: {}", err);
+                }
+            });
+            if let Err(err) = listening {
+                warn!("Could not listen on {:?}: {}", socket, err);
+            }
+        }
+
+        let default_port = params.config.lock().unwrap().default_port;
         Model {
-            stop_tx: Some(stop_tx),
-            evl_tx,
+            stop_tx: Some(params.stop_tx),
+            evl_tx: params.evl_tx,
+            cache: params.cache,
+            config: params.config,
+            config_path: params.config_path,
+            private: params.private,
+            instance_socket: params.instance_socket,
+            bookmarks: Bookmarks::load(params.bookmarks_path, default_port),
+            history: History::load(params.history_path),
             channel,
             sender,
             relm: relm.clone(),
@@ -64,41 +274,232 @@ impl Update for Window {
     fn update(&mut self, event: Msg) {
         match event {
             Msg::OpenUrl(url) => {
-                info!("Opening URL {:?}", url);
-                // TODO: don't unwrap
-                let request = Request::from_url(url).unwrap();
-                info!("Request {:?}", request);
+                // links open in place of the page they're on
+                if let Some(page) = self.current_page() {
+                    self.targets.insert(url.clone(), page.root.clone());
+                }
+                self.open(url);
+            }
+            Msg::OpenInNewTab(url) => {
+                self.targets.remove(&url);
+                self.open(url);
+            }
+            Msg::OpenUrls(urls) => {
+                if urls.is_empty() {
+                    // the home page was validated when the config was loaded
+                    let home_page = self.model.config.lock().unwrap().home_page.clone();
+                    let url = Url::parse(&home_page).unwrap();
+                    self.model.relm.stream().emit(Msg::OpenInNewTab(url));
+                }
+                for url in urls {
+                    self.model.relm.stream().emit(Msg::OpenInNewTab(url));
+                }
+                self.window.present();
+            }
+            Msg::Reload(url) => {
+                // drop the cached copy so the request goes to the network, unless it's the only
+                // copy we're going to get
+                let mut cache = self.model.cache.lock().unwrap();
+                if !cache.is_offline() {
+                    cache.invalidate(&url);
+                }
+                self.model.relm.stream().emit(Msg::OpenUrl(url));
+            }
+            Msg::SaveSite(url) => {
+                let options = match self.ask_mirror_options() {
+                    Some(options) => options,
+                    None => return,
+                };
 
-                // spawn the event on the event loop
-                let msg = Event::MakeRequest(request, self.model.sender.clone());
+                let root = mirror::root_dir(&url, &options);
+                let view = MirrorView::new(&self.model.relm, &root);
+                view.window.set_transient_for(Some(&self.window));
+                view.window.show_all();
+                self.mirror_views.insert(root, view);
+
+                let sender = ReplySender::Window(self.model.sender.clone());
+                let msg = Event::Mirror(url, options, sender);
                 if let Err(err) = self.model.evl_tx.send(msg) {
                     error!("Error sending request: {}", err);
                 }
             }
-            Msg::OpenedUrl(response) => {
+            Msg::BookmarkPage => {
+                let (url, item_type) = match self.current_page() {
+                    Some(page) => (page.url.clone(), page.item_type),
+                    None => return,
+                };
+                let title = bookmarks::default_title(&url);
+                self.bookmark(url, item_type, &title);
+            }
+            Msg::BookmarkLink(row) => {
+                if let (Some(item_type), Some(url)) = (row.item_type, row.url) {
+                    self.bookmark(url, item_type, &row.text);
+                }
+            }
+            Msg::ShowPreferences => {
+                let config = match self.ask_preferences() {
+                    Some(config) => config,
+                    None => return,
+                };
+                let saved = match &self.model.config_path {
+                    Some(path) => config.save(path),
+                    None => config.validate().map_err(Error::Config),
+                };
+                if let Err(err) = saved {
+                    return self.model.relm.stream().emit(Msg::Fail(err));
+                }
+
+                load_css(&self.css_provider, &config);
+                self.window
+                    .resize(config.window.width, config.window.height);
+                let default_port = config.default_port;
+                self.model.cache.lock().unwrap().set_default_port(default_port);
+                self.model.bookmarks.set_default_port(default_port);
+                *self.model.config.lock().unwrap() = config;
+            }
+            Msg::Exchange(exchange) => self.inspector.add(*exchange),
+            Msg::ShowInspector(shown) => self.inspector.panel.set_visible(shown),
+            Msg::InspectExchange(index) => self.inspector.show(index),
+            Msg::ClearExchanges => self.inspector.clear(),
+            Msg::MirrorProgress(progress) => {
+                if let Some(view) = self.mirror_views.get(&progress.root) {
+                    view.update(&progress);
+                }
+            }
+            Msg::OpenedUrl(response, fetched) => {
+                let status = match self.loading.remove(&response.url) {
+                    Some(_) if fetched.is_some() => {
+                        Some(format!("Loaded {} from the cache", response.url))
+                    }
+                    Some(sent) => Some(format!(
+                        "Loaded {} in {}",
+                        response.url,
+                        format_seconds(sent.elapsed())
+                    )),
+                    None => None,
+                };
+                self.set_status("load", status.as_ref().map(String::as_str));
+
+                let replaced = self.targets.remove(&response.url);
+                if let Some(path) = self.save_targets.remove(&response.url) {
+                    return self.save(&response.url, &path, None);
+                }
+                if response.url.scheme() != "about" {
+                    if let Err(err) = self.model.history.record(&response) {
+                        error!("Could not record history: {}", err);
+                    }
+                    self.add_history_completion(&response.url);
+                }
+
                 let child = GtkBox::new(Orientation::Vertical, 0);
-                let stream = self.model.relm.stream().clone();
-                let content = response.into_page(&self.notebook, stream);
+                let content = self.render(&response);
+
+                let toolbar = GtkBox::new(Orientation::Horizontal, 0);
+                let reload_button = Button::new_from_icon_name("view-refresh", IconSize::Button);
+                reload_button.set_tooltip_text(Some("Reload, bypassing the cache"));
+                let url = response.url.clone();
+                connect!(
+                    self.model.relm,
+                    reload_button,
+                    connect_clicked(_),
+                    Msg::Reload(url.clone())
+                );
+                toolbar.add(&reload_button);
 
-                let search_bar = SearchEntry::new();
-                search_bar.set_text(&response.url.to_string());
-                let search_bar_weak = search_bar.downgrade();
+                let save_button = Button::new_from_icon_name("document-save", IconSize::Button);
+                save_button.set_tooltip_text(Some("Save site for offline reading"));
+                let url = response.url.clone();
+                connect!(
+                    self.model.relm,
+                    save_button,
+                    connect_clicked(_),
+                    Msg::SaveSite(url.clone())
+                );
+                save_button.set_sensitive(url.scheme() == "gopher");
+                toolbar.add(&save_button);
+
+                let check_button = Button::new_from_icon_name("emblem-ok", IconSize::Button);
+                check_button.set_tooltip_text(Some("Check links on this page"));
+                let url = response.url.clone();
+                connect!(
+                    self.model.relm,
+                    check_button,
+                    connect_clicked(_),
+                    Msg::CheckLinks(url.clone())
+                );
+                let is_menu = match response.kind {
+                    ResponseKind::Menu(_) => true,
+                    _ => false,
+                };
+                check_button.set_sensitive(is_menu && url.scheme() != "about");
+                toolbar.add(&check_button);
+
+                let up_button = Button::new_from_icon_name("go-up", IconSize::Button);
+                up_button.set_tooltip_text(Some("Up one level"));
+                match up_url(&response.url) {
+                    Some(up) => connect!(
+                        self.model.relm,
+                        up_button,
+                        connect_clicked(_),
+                        Msg::OpenUrl(up.clone())
+                    ),
+                    None => up_button.set_sensitive(false),
+                }
+                toolbar.add(&up_button);
+
+                let address_bar = Entry::new();
+                address_bar.set_text(&response.url.to_string());
                 let stream = self.model.relm.stream().clone();
-                search_bar.connect_activate(move |_| {
-                    let search_bar = upgrade_weak!(search_bar_weak);
-                    println!("content: {:?}", search_bar.get_text());
-                    let url = match search_bar
+                address_bar.connect_activate(move |entry| {
+                    let input = entry
                         .get_text()
-                        .map(|gstr| gstr.as_str().to_owned())
-                        .and_then(|s| Url::parse(s.as_ref()).ok())
-                    {
-                        Some(url) => url,
-                        None => return,
-                    };
-                    stream.emit(Msg::OpenUrl(url));
+                        .map(|text| text.as_str().to_owned())
+                        .unwrap_or_default();
+                    match args::parse_url(&input) {
+                        Ok(url) => stream.emit(Msg::OpenUrl(url)),
+                        Err(err) => mark_invalid(entry, Some(&err)),
+                    }
+                });
+                address_bar.connect_changed(|entry| mark_invalid(entry, None));
+                let completion = EntryCompletion::new();
+                completion.set_model(Some(&self.history_store));
+                completion.set_text_column(0);
+                completion.set_minimum_key_length(2);
+                let history_store = self.history_store.clone();
+                completion.set_match_func(move |_, key, iter| {
+                    history_store
+                        .get_value(iter, 0)
+                        .get::<String>()
+                        .map(|url| url.to_lowercase().contains(&key.to_lowercase()))
+                        .unwrap_or(false)
                 });
-                child.add(&search_bar);
-                child.set_child_packing(&search_bar, false, true, 0, PackType::Start);
+                address_bar.set_completion(Some(&completion));
+                toolbar.add(&address_bar);
+                toolbar.set_child_packing(&address_bar, true, true, 0, PackType::Start);
+
+                if let Some(fetched) = fetched {
+                    let fetched_label = Label::new(
+                        format!("Cached, fetched {}", about::format_age(fetched)).as_str(),
+                    );
+                    fetched_label.set_margin_start(10);
+                    fetched_label.set_margin_end(10);
+                    toolbar.add(&fetched_label);
+                }
+                child.add(&toolbar);
+                child.set_child_packing(&toolbar, false, true, 0, PackType::Start);
+
+                let warnings = GtkBox::new(Orientation::Vertical, 2);
+                warnings.set_no_show_all(true);
+                child.add(&warnings);
+                child.set_child_packing(&warnings, false, true, 0, PackType::Start);
+
+                let filter = FilterBar::new(&self.model.relm);
+                child.add(&filter.row);
+                child.set_child_packing(&filter.row, false, true, 0, PackType::Start);
+
+                let find = FindBar::new(&self.model.relm);
+                child.add(&find.bar);
+                child.set_child_packing(&find.bar, false, true, 0, PackType::Start);
 
                 let content_scroll = ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT);
                 content_scroll.add(&content);
@@ -106,18 +507,204 @@ impl Update for Window {
                 child.set_child_packing(&content_scroll, true, true, 0, PackType::End);
 
                 let label = Label::new("new tab");
-                let n = self.notebook.append_page(&child, Some(&label));
+                let position =
+                    replaced.and_then(|root| Some((self.notebook.page_num(&root)?, root)));
+                let n = match position {
+                    Some((n, root)) => {
+                        self.notebook.remove_page(Some(n));
+                        self.pages.retain(|page| page.root != root);
+                        self.notebook.insert_page(&child, Some(&label), Some(n))
+                    }
+                    None => self.notebook.append_page(&child, Some(&label)),
+                };
+                self.pages.push(Page {
+                    root: child.clone(),
+                    url: response.url.clone(),
+                    item_type: response.item_type,
+                    content: content_scroll,
+                    text_view: text_view_in(content.upcast_ref()),
+                    body: content,
+                    rows: rows_of(&response),
+                    cursor: None,
+                    number: String::new(),
+                    warnings: warnings.clone(),
+                    filter,
+                    find,
+                    _monitor: self.watch(&response.url),
+                });
                 self.notebook.set_tab_reorderable(&child, true);
                 self.notebook.show_all();
+                if let Some(page) = self.pages.last() {
+                    page.filter.row.set_visible(!page.rows.is_empty());
+                    if let Some(view) = &page.text_view {
+                        self.connect_keys(view, !page.rows.is_empty());
+                        self.connect_page_menu(view, &page.url);
+                        view.grab_focus();
+                    }
+                }
+                self.show_warnings(&warnings, &response.url);
                 self.notebook.set_current_page(n);
             }
-            Msg::Fail(err) => error!("error: {:?}", err),
+            Msg::Progress(url, bytes) => {
+                if let Some(sent) = self.loading.get(&url) {
+                    let status = format!(
+                        "Loading {}: {} in {}",
+                        url,
+                        about::format_size(bytes),
+                        format_seconds(sent.elapsed())
+                    );
+                    self.set_status("load", Some(&status));
+                }
+            }
+            Msg::HoverLink(link) => {
+                let status = link.map(|(item_type, url)| describe_link(item_type, &url));
+                self.set_status("link", status.as_ref().map(String::as_str));
+            }
+            Msg::LinkMenu(row, button, time) => self.show_link_menu(row, button, time),
+            Msg::CopyUrl(url) => {
+                let clipboard = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+                clipboard.set_text(url.as_str());
+            }
+            Msg::SaveTarget(url) => {
+                if let Some(path) = self.ask_save_path("Save target as", &file_name(&url)) {
+                    self.save_targets.insert(url.clone(), path);
+                    self.open(url);
+                }
+            }
+            Msg::ShowItemInfo(row) => self.show_item_info(&row),
+            Msg::ViewSource(url) => self.show_source(&url),
+            Msg::SavePage(url) => {
+                if let Some((path, format)) = self.ask_save_page(&url) {
+                    self.save(&url, &path, format);
+                }
+            }
+            Msg::LocalChanged(url) => {
+                let encodings = self.model.config.lock().unwrap().encodings();
+                let response = match local::open(&url, &encodings) {
+                    Ok(response) => response,
+                    Err(err) => {
+                        warn!("Could not reload {}: {}", url, err);
+                        return;
+                    }
+                };
+                // replace the pages in place rather than opening another tab for every save
+                let changed = (0..self.pages.len())
+                    .filter(|&n| self.pages[n].url == url)
+                    .collect::<Vec<_>>();
+                for n in changed {
+                    let body = self.render(&response);
+                    let text_view = text_view_in(body.upcast_ref());
+                    if let Some(view) = &text_view {
+                        self.connect_keys(view, true);
+                        self.connect_page_menu(view, &url);
+                    }
+                    let page = &mut self.pages[n];
+                    if let Some(old) = page.content.get_child() {
+                        page.content.remove(&old);
+                    }
+                    page.content.add(&body);
+                    page.content.show_all();
+                    page.text_view = text_view;
+                    page.body = body;
+                    page.rows = rows_of(&response);
+                    page.cursor = None;
+                    page.filter();
+                    page.find();
+                    self.show_warnings(&self.pages[n].warnings, &url);
+                }
+            }
+            Msg::CheckLinks(url) => {
+                info!("Checking links on {}", url);
+                let options = CheckOptions::new(&self.model.config.lock().unwrap());
+                let sender = ReplySender::Window(self.model.sender.clone());
+                let msg = Event::CheckLinks(url, options, sender);
+                if let Err(err) = self.model.evl_tx.send(msg) {
+                    error!("Error sending request: {}", err);
+                }
+            }
+            Msg::CheckedLinks(url, reports) => self.show_link_reports(&url, &reports),
+            Msg::Find => {
+                if let Some(page) = self.current_page() {
+                    page.find.bar.set_search_mode(true);
+                    page.find.entry.grab_focus();
+                }
+            }
+            Msg::FindChanged => {
+                if let Some(page) = self.current_page_mut() {
+                    page.find();
+                }
+            }
+            Msg::FindNext(backwards) => {
+                if let Some(page) = self.current_page_mut() {
+                    page.step(backwards);
+                }
+            }
+            Msg::FindClosed => {
+                if let Some(page) = self.current_page_mut() {
+                    page.clear_found();
+                }
+            }
+            Msg::FilterChanged => {
+                if let Some(page) = self.current_page() {
+                    page.filter();
+                }
+            }
+            Msg::MoveCursor(delta) => {
+                let link = self.current_page_mut().and_then(|page| {
+                    page.number.clear();
+                    page.move_cursor(delta);
+                    let row = page.rows.get(page.cursor?)?;
+                    Some((row.item_type?, row.url.clone()?))
+                });
+                let status = link.map(|(item_type, url)| describe_link(item_type, &url));
+                self.set_status("link", status.as_ref().map(String::as_str));
+            }
+            Msg::FollowLink => {
+                if let Some(url) = self.current_page_mut().and_then(Page::chosen_link) {
+                    self.model.relm.stream().emit(Msg::OpenUrl(url));
+                }
+            }
+            Msg::TypeDigit(digit) => {
+                if let Some(page) = self.current_page_mut() {
+                    page.number.push(digit);
+                }
+            }
+            Msg::GoUp => {
+                if let Some(url) = self.current_page().and_then(|page| up_url(&page.url)) {
+                    self.model.relm.stream().emit(Msg::OpenUrl(url));
+                }
+            }
+            Msg::SetOffline(offline) => {
+                info!("Offline mode: {}", offline);
+                self.model.cache.lock().unwrap().set_offline(offline);
+            }
+            Msg::Fail(err) => {
+                error!("error: {:?}", err);
+                // replies don't say which request failed, so stop waiting for all of them
+                self.loading.clear();
+                self.save_targets.clear();
+                self.set_status("load", None);
+                let dialog = MessageDialog::new(
+                    Some(&self.window),
+                    DialogFlags::DESTROY_WITH_PARENT,
+                    MessageType::Error,
+                    ButtonsType::Close,
+                    &err.to_string(),
+                );
+                dialog.run();
+                dialog.destroy();
+            }
             Msg::Quit => {
                 // hack to take stop_tx
                 let stop_tx = self.model.stop_tx.take();
                 if let Err(err) = stop_tx.unwrap().send(()) {
                     panic!("Error sending stop: {:?}", err);
                 }
+                if let Some(socket) = &self.model.instance_socket {
+                    if let Err(err) = fs::remove_file(socket) {
+                        warn!("Could not remove {:?}: {}", socket, err);
+                    }
+                }
 
                 gtk::main_quit();
             }
@@ -125,6 +712,1726 @@ impl Update for Window {
     }
 }
 
+impl Window {
+    /// Requests a page, which is shown once `Msg::OpenedUrl` comes back with it.
+    fn open(&mut self, url: Url) {
+        info!("Opening URL {:?}", url);
+        if url.scheme() == "about" {
+            let page = about::page(
+                &url,
+                &self.model.cache.lock().unwrap(),
+                &self.model.bookmarks,
+                &self.model.history,
+            );
+            match page {
+                Some(response) => self
+                    .model
+                    .relm
+                    .stream()
+                    .emit(Msg::OpenedUrl(response, None)),
+                None => error!("No such page: {}", url),
+            }
+            return;
+        }
+        let (default_port, encodings) = {
+            let config = self.model.config.lock().unwrap();
+            (config.default_port, config.encodings())
+        };
+        if url.scheme() == "file" {
+            match local::open(&url, &encodings) {
+                Ok(response) => self
+                    .model
+                    .relm
+                    .stream()
+                    .emit(Msg::OpenedUrl(response, None)),
+                Err(err) => self.model.relm.stream().emit(Msg::Fail(err)),
+            }
+            return;
+        }
+
+        let request = match Request::with_default_port(url.clone(), default_port) {
+            Ok(request) => request,
+            Err(err) => return self.model.relm.stream().emit(Msg::Fail(err.into())),
+        };
+        info!("Request {:?}", request);
+        self.set_status("load", Some(&format!("Loading {}...", url)));
+        self.loading.insert(url, Instant::now());
+
+        // spawn the event on the event loop
+        let sender = ReplySender::Window(self.model.sender.clone());
+        let msg = Event::MakeRequest(request, sender);
+        if let Err(err) = self.model.evl_tx.send(msg) {
+            error!("Error sending request: {}", err);
+        }
+    }
+
+    fn render(&self, response: &Response) -> GtkBox {
+        let cache = self.model.cache.lock().unwrap();
+        let offline = cache.is_offline();
+        let is_available = |url: &Url| !offline || cache.contains(url);
+        let renderer = GtkRenderer {
+            stream: self.model.relm.stream().clone(),
+            is_available: &is_available,
+        };
+        renderer.render(response)
+    }
+
+    /// Replaces the status bar message for `context`, which is "link" or "load", or removes it.
+    /// Messages about links go on top of those about loading while the pointer is on a link.
+    fn set_status(&self, context: &str, text: Option<&str>) {
+        let id = self.statusbar.get_context_id(context);
+        self.statusbar.remove_all(id);
+        if let Some(text) = text {
+            self.statusbar.push(id, text);
+        }
+    }
+
+    /// Lists the lint warnings for a local gophermap, hiding the list if there are none.
+    fn show_warnings(&self, warnings: &GtkBox, url: &Url) {
+        for child in warnings.get_children() {
+            warnings.remove(&child);
+        }
+        let found = match local::gophermap_path(url) {
+            Some(map_path) => {
+                let encodings = self.model.config.lock().unwrap().encodings();
+                local::lint(&map_path, &encodings).unwrap_or_else(|err| {
+                    warn!("Could not lint {}: {}", map_path.display(), err);
+                    Vec::new()
+                })
+            }
+            None => Vec::new(),
+        };
+        if found.is_empty() {
+            warnings.hide();
+            return;
+        }
+
+        for warning in found {
+            let row = GtkBox::new(Orientation::Horizontal, 5);
+            row.set_margin_start(10);
+            row.add(&Image::new_from_icon_name(
+                "dialog-warning",
+                IconSize::Button,
+            ));
+            let label = Label::new(warning.to_string().as_str());
+            label.set_halign(Align::Start);
+            label.set_selectable(true);
+            row.add(&label);
+            warnings.add(&row);
+        }
+        warnings.show_all();
+    }
+
+    /// Makes the keys from the `[keys]` config table work on a page. Keys for moving between
+    /// links are left to the text view on pages that aren't menus, so that they still scroll.
+    fn connect_keys(&self, view: &TextView, is_menu: bool) {
+        let bindings = key_bindings(&self.model.config.lock().unwrap().keys);
+        let stream = self.model.relm.stream().clone();
+        view.connect_key_press_event(move |_, event| {
+            let keyval = gdk::keyval_to_lower(event.get_keyval());
+            let modifiers = event.get_state() & gtk::accelerator_get_default_mod_mask();
+            let bound = bindings.iter().find(|&&(key, mods, action)| {
+                // symbols like "/" need Shift on some keyboards, which shouldn't matter
+                let shifted = mods == modifiers - ModifierType::SHIFT_MASK
+                    && gdk::keyval_to_upper(keyval) == keyval;
+                key == keyval
+                    && (mods == modifiers || shifted)
+                    && (is_menu || !action.is_for_menus())
+            });
+            if let Some(&(_, _, action)) = bound {
+                stream.emit(action.msg());
+                return Inhibit(true);
+            }
+
+            let digit = gdk::keyval_to_unicode(keyval).filter(char::is_ascii_digit);
+            let plain = !modifiers.intersects(ModifierType::CONTROL_MASK | ModifierType::MOD1_MASK);
+            match digit {
+                Some(digit) if is_menu && plain => {
+                    stream.emit(Msg::TypeDigit(digit));
+                    Inhibit(true)
+                }
+                _ => Inhibit(false),
+            }
+        });
+    }
+
+    /// Watches the directory of a local gophermap so that its page is redrawn when it's saved.
+    ///
+    /// The directory is watched rather than the file because many editors save by writing a new
+    /// file and renaming it.
+    fn watch(&self, url: &Url) -> Option<FileMonitor> {
+        let map_path = local::gophermap_path(url)?;
+        let dir = gio::File::new_for_path(map_path.parent()?);
+        let cancellable = None::<&gio::Cancellable>;
+        let monitor = match dir.monitor_directory(FileMonitorFlags::NONE, cancellable) {
+            Ok(monitor) => monitor,
+            Err(err) => {
+                warn!("Could not watch {}: {}", map_path.display(), err);
+                return None;
+            }
+        };
+
+        let stream = self.model.relm.stream().clone();
+        let url = url.clone();
+        monitor.connect_changed(move |_, file, _, event| match event {
+            FileMonitorEvent::ChangesDoneHint | FileMonitorEvent::Created
+                if file.get_path().as_ref() == Some(&map_path) =>
+            {
+                stream.emit(Msg::LocalChanged(url.clone()))
+            }
+            _ => (),
+        });
+        Some(monitor)
+    }
+
+    fn current_page(&self) -> Option<&Page> {
+        let current = self.notebook.get_current_page()?;
+        let widget = self.notebook.get_nth_page(Some(current))?;
+        self.pages
+            .iter()
+            .find(|page| page.root.clone().upcast::<gtk::Widget>() == widget)
+    }
+
+    fn current_page_mut(&mut self) -> Option<&mut Page> {
+        let current = self.notebook.get_current_page()?;
+        let widget = self.notebook.get_nth_page(Some(current))?;
+        self.pages
+            .iter_mut()
+            .find(|page| page.root.clone().upcast::<gtk::Widget>() == widget)
+    }
+
+    /// Moves `url` to the top of the address bar completions.
+    fn add_history_completion(&self, url: &Url) {
+        let url = url.to_string();
+        if let Some(iter) = self.history_store.get_iter_first() {
+            loop {
+                if self
+                    .history_store
+                    .get_value(&iter, 0)
+                    .get::<String>()
+                    .as_ref()
+                    == Some(&url)
+                {
+                    self.history_store.remove(&iter);
+                    break;
+                }
+                if !self.history_store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        self.history_store
+            .insert_with_values(Some(0), &[0], &[&url]);
+    }
+
+    /// Rebuilds the bookmarks menu in the header bar from the current bookmarks.
+    fn fill_bookmarks_menu(&self) {
+        for child in self.bookmarks_menu.get_children() {
+            self.bookmarks_menu.remove(&child);
+        }
+
+        let add_item = MenuItem::new_with_label("Bookmark this page (Ctrl+D)");
+        connect!(
+            self.model.relm,
+            add_item,
+            connect_activate(_),
+            Msg::BookmarkPage
+        );
+        self.bookmarks_menu.append(&add_item);
+        let show_item = MenuItem::new_with_label("Show all bookmarks");
+        let about_url = Url::parse("about:bookmarks").unwrap();
+        connect!(
+            self.model.relm,
+            show_item,
+            connect_activate(_),
+            Msg::OpenUrl(about_url.clone())
+        );
+        self.bookmarks_menu.append(&show_item);
+
+        let bookmarks = &self.model.bookmarks;
+        if !bookmarks.entries().is_empty() {
+            self.bookmarks_menu.append(&SeparatorMenuItem::new());
+        }
+        for folder in bookmarks.folders() {
+            let menu = match folder {
+                Some(name) => {
+                    let submenu = Menu::new();
+                    let folder_item = MenuItem::new_with_label(name);
+                    folder_item.set_submenu(Some(&submenu));
+                    self.bookmarks_menu.append(&folder_item);
+                    submenu
+                }
+                None => self.bookmarks_menu.clone(),
+            };
+            for bookmark in bookmarks.in_folder(folder) {
+                let item = MenuItem::new_with_label(&bookmark.title);
+                item.set_tooltip_text(Some(bookmark.url.as_str()));
+                let url = bookmark.url.clone();
+                connect!(
+                    self.model.relm,
+                    item,
+                    connect_activate(_),
+                    Msg::OpenUrl(url.clone())
+                );
+                menu.append(&item);
+            }
+        }
+        self.bookmarks_menu.show_all();
+    }
+
+    /// Asks for the details of a new bookmark and adds it.
+    fn bookmark(&mut self, url: Url, item_type: ItemType, name: &str) {
+        if let Some(bookmark) = self.ask_bookmark(url, item_type, name) {
+            if let Err(err) = self.model.bookmarks.add(bookmark) {
+                return self.model.relm.stream().emit(Msg::Fail(err));
+            }
+            self.fill_bookmarks_menu();
+        }
+    }
+
+    /// Asks for the title, folder and tags of a new bookmark, starting with `name` as the title.
+    fn ask_bookmark(&self, url: Url, item_type: ItemType, name: &str) -> Option<Bookmark> {
+        let dialog = Dialog::new();
+        dialog.set_title("Add bookmark");
+        dialog.set_transient_for(Some(&self.window));
+        dialog.set_modal(true);
+        dialog.add_button("Cancel", ResponseType::Cancel.into());
+        dialog.add_button("Save", ResponseType::Accept.into());
+        dialog.set_default_response(ResponseType::Accept.into());
+
+        let grid = Grid::new();
+        grid.set_row_spacing(5);
+        grid.set_column_spacing(10);
+        grid.set_border_width(10);
+        let title = Entry::new();
+        title.set_text(name);
+        title.set_activates_default(true);
+        let folder = Entry::new();
+        folder.set_placeholder_text(Some("none"));
+        let tags = Entry::new();
+        tags.set_placeholder_text(Some("comma separated"));
+
+        let rows = [("Title", &title), ("Folder", &folder), ("Tags", &tags)];
+        for (i, (text, entry)) in rows.iter().enumerate() {
+            let label = Label::new(*text);
+            label.set_halign(Align::Start);
+            grid.attach(&label, 0, i as i32, 1, 1);
+            grid.attach(*entry, 1, i as i32, 1, 1);
+        }
+        dialog.get_content_area().add(&grid);
+        dialog.show_all();
+
+        let text = |entry: &Entry| {
+            entry
+                .get_text()
+                .map(|text| text.as_str().trim().to_owned())
+                .unwrap_or_default()
+        };
+        let response = dialog.run();
+        let bookmark = if response == ResponseType::Accept.into() {
+            let folder = text(&folder);
+            Some(Bookmark {
+                title: text(&title),
+                url,
+                item_type,
+                folder: if folder.is_empty() {
+                    None
+                } else {
+                    Some(folder)
+                },
+                tags: text(&tags)
+                    .split(',')
+                    .map(|tag| tag.trim().to_owned())
+                    .filter(|tag| !tag.is_empty())
+                    .collect(),
+            })
+        } else {
+            None
+        };
+        dialog.destroy();
+        bookmark
+    }
+
+    /// Saves a binary file to the download directory, returning where it went.
+    /// Shows the preferences, returning the new settings if the user saved them.
+    fn ask_preferences(&self) -> Option<Config> {
+        let current = self.model.config.lock().unwrap().clone();
+
+        let dialog = Dialog::new();
+        dialog.set_title("Preferences");
+        dialog.set_transient_for(Some(&self.window));
+        dialog.set_modal(true);
+        dialog.add_button("Cancel", ResponseType::Cancel.into());
+        dialog.add_button("Save", ResponseType::Accept.into());
+        dialog.set_default_response(ResponseType::Accept.into());
+
+        let grid = Grid::new();
+        grid.set_row_spacing(5);
+        grid.set_column_spacing(10);
+        grid.set_border_width(10);
+        let home_page = Entry::new();
+        home_page.set_text(&current.home_page);
+        home_page.set_activates_default(true);
+        let default_port = SpinButton::new_with_range(1.0, 65535.0, 1.0);
+        default_port.set_value(current.default_port as f64);
+        let timeout = SpinButton::new_with_range(1.0, 600.0, 1.0);
+        timeout.set_value(current.timeout as f64);
+        let font =
+            FontButton::new_with_font(&format!("{} {}", current.font.family, current.font.size));
+        let color_entry = |color: &Option<String>| {
+            let entry = Entry::new();
+            entry.set_placeholder_text(Some("theme default"));
+            if let Some(color) = color {
+                entry.set_text(color);
+            }
+            entry
+        };
+        let foreground = color_entry(&current.colors.foreground);
+        let background = color_entry(&current.colors.background);
+        let link = color_entry(&current.colors.link);
+        let encodings = Entry::new();
+        encodings.set_text(&current.encodings.join(", "));
+        let download_dir =
+            FileChooserButton::new("Download folder", FileChooserAction::SelectFolder);
+        download_dir.set_filename(current.download_dir());
+        let width = SpinButton::new_with_range(200.0, 10000.0, 1.0);
+        width.set_value(current.window.width as f64);
+        let height = SpinButton::new_with_range(150.0, 10000.0, 1.0);
+        height.set_value(current.window.height as f64);
+
+        let rows: Vec<(&str, gtk::Widget)> = vec![
+            ("Home page", home_page.clone().upcast()),
+            ("Default port", default_port.clone().upcast()),
+            ("Timeout (s)", timeout.clone().upcast()),
+            ("Font", font.clone().upcast()),
+            ("Text color", foreground.clone().upcast()),
+            ("Background color", background.clone().upcast()),
+            ("Link color", link.clone().upcast()),
+            ("Encodings", encodings.clone().upcast()),
+            ("Download folder", download_dir.clone().upcast()),
+            ("Window width", width.clone().upcast()),
+            ("Window height", height.clone().upcast()),
+        ];
+        for (i, (text, widget)) in rows.iter().enumerate() {
+            let label = Label::new(*text);
+            label.set_halign(Align::Start);
+            grid.attach(&label, 0, i as i32, 1, 1);
+            grid.attach(widget, 1, i as i32, 1, 1);
+        }
+        dialog.get_content_area().add(&grid);
+        dialog.show_all();
+
+        let text = |entry: &Entry| {
+            entry
+                .get_text()
+                .map(|text| text.as_str().trim().to_owned())
+                .unwrap_or_default()
+        };
+        let color = |entry: &Entry| Some(text(entry)).filter(|color| !color.is_empty());
+        let response = dialog.run();
+        let config = if response == ResponseType::Accept.into() {
+            // font names look like "DejaVu Sans Mono 11"
+            let font_name = font
+                .get_font()
+                .map(|name| name.as_str().to_owned())
+                .unwrap_or_default();
+            let mut parts = font_name.rsplitn(2, ' ');
+            let font = match (parts.next().map(str::parse), parts.next()) {
+                (Some(Ok(size)), Some(family)) => config::Font {
+                    family: family.to_owned(),
+                    size,
+                },
+                _ => current.font.clone(),
+            };
+
+            Some(Config {
+                home_page: text(&home_page),
+                default_port: default_port.get_value_as_int() as u16,
+                timeout: timeout.get_value_as_int() as u64,
+                encodings: text(&encodings)
+                    .split(',')
+                    .map(|label| label.trim().to_owned())
+                    .filter(|label| !label.is_empty())
+                    .collect(),
+                // the default folder isn't written to the config, so it follows the system's
+                download_dir: download_dir
+                    .get_filename()
+                    .filter(|dir| *dir != current.download_dir())
+                    .or_else(|| current.download_dir.clone()),
+                font,
+                colors: config::Colors {
+                    foreground: color(&foreground),
+                    background: color(&background),
+                    link: color(&link),
+                },
+                window: config::WindowSize {
+                    width: width.get_value_as_int(),
+                    height: height.get_value_as_int(),
+                },
+                ..current
+            })
+        } else {
+            None
+        };
+        dialog.destroy();
+        config
+    }
+
+    /// Pops up the menu for a link that was right-clicked.
+    fn show_link_menu(&self, row: MenuRow, button: u32, time: u32) {
+        let (item_type, url) = match (row.item_type, &row.url) {
+            (Some(item_type), Some(url)) => (item_type, url.clone()),
+            _ => return,
+        };
+        let menu = Menu::new();
+
+        let open_item = MenuItem::new_with_label("Open in new tab");
+        let open_url = url.clone();
+        connect!(
+            self.model.relm,
+            open_item,
+            connect_activate(_),
+            Msg::OpenInNewTab(open_url.clone())
+        );
+        menu.append(&open_item);
+        let copy_item = MenuItem::new_with_label("Copy link URL");
+        let copy_url = url.clone();
+        connect!(
+            self.model.relm,
+            copy_item,
+            connect_activate(_),
+            Msg::CopyUrl(copy_url.clone())
+        );
+        menu.append(&copy_item);
+        let save_item = MenuItem::new_with_label("Save target as...");
+        save_item.set_sensitive(can_save(item_type, &url));
+        connect!(
+            self.model.relm,
+            save_item,
+            connect_activate(_),
+            Msg::SaveTarget(url.clone())
+        );
+        menu.append(&save_item);
+
+        menu.append(&SeparatorMenuItem::new());
+        let bookmark_item = MenuItem::new_with_label("Bookmark link");
+        let bookmark_row = row.clone();
+        connect!(
+            self.model.relm,
+            bookmark_item,
+            connect_activate(_),
+            Msg::BookmarkLink(bookmark_row.clone())
+        );
+        menu.append(&bookmark_item);
+        let info_item = MenuItem::new_with_label("Show item info");
+        connect!(
+            self.model.relm,
+            info_item,
+            connect_activate(_),
+            Msg::ShowItemInfo(row.clone())
+        );
+        menu.append(&info_item);
+
+        menu.show_all();
+        menu.popup_easy(button, time);
+    }
+
+    /// Adds actions for the whole page to the menu of its text view.
+    fn connect_page_menu(&self, view: &TextView, url: &Url) {
+        let stream = self.model.relm.stream().clone();
+        let url = url.clone();
+        view.connect_populate_popup(move |_, popup| {
+            let menu = match popup.clone().downcast::<Menu>() {
+                Ok(menu) => menu,
+                Err(_) => return,
+            };
+            // about: pages are made up on the spot, there's nothing underneath them
+            let has_source = url.scheme() != "about";
+            let items: [(&str, fn(Url) -> Msg, bool); 4] = [
+                ("View raw source", Msg::ViewSource, has_source),
+                ("Save page as...", Msg::SavePage, has_source),
+                ("Copy URL", Msg::CopyUrl, true),
+                ("Reload", Msg::Reload, true),
+            ];
+            menu.append(&SeparatorMenuItem::new());
+            for &(label, msg, sensitive) in items.iter() {
+                let item = MenuItem::new_with_label(label);
+                item.set_sensitive(sensitive);
+                let stream = stream.clone();
+                let url = url.clone();
+                item.connect_activate(move |_| stream.emit(msg(url.clone())));
+                menu.append(&item);
+            }
+            menu.show_all();
+        });
+    }
+
+    /// Shows what is known about a link: its type, where it goes and whether it's cached.
+    fn show_item_info(&self, row: &MenuRow) {
+        let (item_type, url) = match (row.item_type, &row.url) {
+            (Some(item_type), Some(url)) => (item_type, url),
+            _ => return,
+        };
+        let mut info = vec![
+            ("Name", row.text.clone()),
+            (
+                "Type",
+                format!("{} ({})", item_type.name(), item_type.encode() as char),
+            ),
+            ("URL", url.to_string()),
+        ];
+        if let Some(host) = url.host_str() {
+            info.push(("Host", host.to_owned()));
+        }
+        if let Some(port) = url.port_or_known_default() {
+            info.push(("Port", port.to_string()));
+        }
+        if url.scheme() == "gopher" {
+            if let Ok(request) = Request::from_url(url.clone()) {
+                info.push(("Selector", request.selector()));
+            }
+        }
+        let cached = self
+            .model
+            .cache
+            .lock()
+            .unwrap()
+            .list()
+            .into_iter()
+            .find(|entry| &entry.url == url);
+        let cached = match cached {
+            Some(entry) => format!(
+                "{}, fetched {}",
+                about::format_size(entry.size),
+                about::format_age(entry.fetched)
+            ),
+            None => "no".to_owned(),
+        };
+        info.push(("Cached", cached));
+
+        let dialog = Dialog::new();
+        dialog.set_title(&row.text);
+        dialog.set_transient_for(Some(&self.window));
+        dialog.add_button("Close", ResponseType::Close.into());
+        dialog.connect_response(|dialog, _| dialog.destroy());
+
+        let grid = Grid::new();
+        grid.set_row_spacing(5);
+        grid.set_column_spacing(10);
+        grid.set_border_width(10);
+        for (i, (name, value)) in info.iter().enumerate() {
+            let label = Label::new(*name);
+            label.set_halign(Align::Start);
+            grid.attach(&label, 0, i as i32, 1, 1);
+            let value = Label::new(value.as_str());
+            value.set_halign(Align::Start);
+            value.set_selectable(true);
+            grid.attach(&value, 1, i as i32, 1, 1);
+        }
+        dialog.get_content_area().add(&grid);
+        dialog.show_all();
+    }
+
+    /// Shows the bytes a page was made from in a window of its own, with tabs, line endings and
+    /// bytes that aren't UTF-8 marked. Menus are shown next to the entries parsed from them, and
+    /// lines the parser left out are highlighted.
+    fn show_source(&self, url: &Url) {
+        let body = match self.raw_body(url) {
+            Some(body) => body,
+            None => {
+                let err = Error::NoSource(url.clone());
+                return self.model.relm.stream().emit(Msg::Fail(err));
+            }
+        };
+        let entries = match self.page_response(url).map(|response| response.kind) {
+            Some(ResponseKind::Menu(entries)) => Some(entries),
+            _ => None,
+        };
+        let shown = &body[..body.len().min(source::MAX_SHOWN)];
+        let lines = source::lines(shown, entries.is_some());
+
+        let window = gtk::Window::new(WindowType::Toplevel);
+        window.set_title(&format!("Source of {}", url));
+        window.set_transient_for(Some(&self.window));
+        window.set_default_size(900, 500);
+        let vbox = GtkBox::new(Orientation::Vertical, 5);
+        vbox.set_border_width(5);
+
+        let rejected = lines.iter().filter(|line| line.rejected).count();
+        let mut summary = format!("{}, {} lines", about::format_size(body.len()), lines.len());
+        if entries.is_some() {
+            summary += &format!(
+                ", {} left out of the menu as bad lines (highlighted)",
+                rejected
+            );
+        }
+        if shown.len() < body.len() {
+            summary += &format!(", first {} shown", about::format_size(shown.len()));
+        }
+        let label = Label::new(summary.as_str());
+        label.set_halign(Align::Start);
+        vbox.pack_start(&label, false, false, 0);
+
+        let text_view = TextView::new();
+        text_view.set_editable(false);
+        text_view.set_property_monospace(true);
+        if let Some(buffer) = text_view.get_buffer() {
+            insert_source(&buffer, &lines);
+        }
+        let raw_scroll = ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT);
+        raw_scroll.add(&text_view);
+
+        match entries {
+            Some(entries) => {
+                let paned = Paned::new(Orientation::Horizontal);
+                paned.pack1(&raw_scroll, true, false);
+                paned.pack2(&entry_list(&entries), true, false);
+                paned.set_position(450);
+                vbox.pack_start(&paned, true, true, 0);
+            }
+            None => vbox.pack_start(&raw_scroll, true, true, 0),
+        }
+        window.add(&vbox);
+        window.show_all();
+    }
+
+    /// The response a page was made from, parsed again from the file of a local page or the
+    /// response in the cache.
+    fn page_response(&self, url: &Url) -> Option<Response> {
+        let encodings = self.model.config.lock().unwrap().encodings();
+        match url.scheme() {
+            "file" => local::open(url, &encodings).ok(),
+            "gopher" => {
+                let item_type = Request::from_url(url.clone()).ok()?.item_type;
+                let body = self.raw_body(url)?;
+                Response::decode(url.clone(), item_type, body, &encodings).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// The bytes a page was made from: the file of a local page, or the response to a request,
+    /// which is kept in the cache.
+    fn raw_body(&self, url: &Url) -> Option<Vec<u8>> {
+        match url.scheme() {
+            "file" => {
+                let path = local::gophermap_path(url).or_else(|| url.to_file_path().ok())?;
+                fs::read(path).ok()
+            }
+            "gopher" => Some(self.model.cache.lock().unwrap().get_any(url)?.body),
+            _ => None,
+        }
+    }
+
+    /// A dialog asking where to save a file, starting in the download folder.
+    fn save_dialog(&self, title: &str, name: &str) -> FileChooserDialog {
+        let dialog =
+            FileChooserDialog::new(Some(title), Some(&self.window), FileChooserAction::Save);
+        dialog.add_button("Cancel", ResponseType::Cancel.into());
+        dialog.add_button("Save", ResponseType::Accept.into());
+        dialog.set_default_response(ResponseType::Accept.into());
+        dialog.set_do_overwrite_confirmation(true);
+        dialog.set_current_folder(self.model.config.lock().unwrap().download_dir());
+        dialog.set_current_name(name);
+        dialog
+    }
+
+    /// Asks where to save a file.
+    fn ask_save_path(&self, title: &str, name: &str) -> Option<PathBuf> {
+        let dialog = self.save_dialog(title, name);
+        let path = chosen_path(&dialog);
+        dialog.destroy();
+        path
+    }
+
+    /// Asks where to save a page and what to save it as: the bytes it was made from, or one of
+    /// `convert::SAVE_FORMATS`.
+    fn ask_save_page(&self, url: &Url) -> Option<(PathBuf, Option<Format>)> {
+        let name = file_name(url);
+        let dialog = self.save_dialog("Save page as", &name);
+        let formats = ComboBoxText::new();
+        formats.append(Some("raw"), "As received");
+        for &(id, label) in convert::SAVE_FORMATS {
+            formats.append(Some(id), label);
+        }
+        formats.set_active_id(Some("raw"));
+        let chooser = dialog.clone();
+        formats.connect_changed(move |formats| match chosen_format(formats) {
+            Some(format) => chooser.set_current_name(&convert::file_name(&name, format)),
+            None => chooser.set_current_name(&name),
+        });
+        let row = GtkBox::new(Orientation::Horizontal, 5);
+        row.pack_start(&Label::new("Save as:"), false, false, 0);
+        row.pack_start(&formats, false, false, 0);
+        row.show_all();
+        dialog.set_extra_widget(&row);
+
+        let chosen = chosen_path(&dialog).map(|path| (path, chosen_format(&formats)));
+        dialog.destroy();
+        chosen
+    }
+
+    /// Saves a page: the bytes `url` was made from, as they came from the server, or the page
+    /// converted to `format`.
+    fn save(&self, url: &Url, path: &Path, format: Option<Format>) {
+        let body = match format {
+            None => self.raw_body(url),
+            Some(format) => self
+                .page_response(url)
+                .map(|response| convert::convert(&response, format)),
+        };
+        let saved = match body {
+            Some(body) => fs::write(path, body).map_err(Error::from),
+            None => Err(Error::NoSource(url.clone())),
+        };
+        match saved {
+            Ok(()) => {
+                let status = format!("Saved {} to {}", url, path.display());
+                self.set_status("load", Some(&status));
+            }
+            Err(err) => self.model.relm.stream().emit(Msg::Fail(err)),
+        }
+    }
+
+    /// Lists how the links on a menu turned out, problems first. Clicking a link opens it.
+    fn show_link_reports(&self, url: &Url, reports: &[LinkReport]) {
+        let dialog = Dialog::new();
+        dialog.set_title(&format!("Links on {}", url));
+        dialog.set_transient_for(Some(&self.window));
+        dialog.set_default_size(600, 400);
+        dialog.add_button("Close", ResponseType::Close.into());
+        dialog.connect_response(|dialog, _| dialog.destroy());
+
+        let container = GtkBox::new(Orientation::Vertical, 5);
+        container.set_border_width(10);
+        let summary = Label::new(check::summary(reports).as_str());
+        summary.set_halign(Align::Start);
+        summary.set_line_wrap(true);
+        container.add(&summary);
+
+        let grid = Grid::new();
+        grid.set_row_spacing(5);
+        grid.set_column_spacing(10);
+        let mut sorted = reports.iter().collect::<Vec<_>>();
+        sorted.sort_by_key(|report| !report.status.is_problem());
+        for (i, report) in sorted.into_iter().enumerate() {
+            let status = Label::new(report.status.name());
+            status.set_halign(Align::Start);
+            grid.attach(&status, 0, i as i32, 1, 1);
+
+            let link = Button::new_with_label(&report.display);
+            link.set_relief(gtk::ReliefStyle::None);
+            link.set_halign(Align::Start);
+            link.set_tooltip_text(Some(report.url.as_str()));
+            if let Ok(target) = Url::parse(&report.url) {
+                connect!(
+                    self.model.relm,
+                    link,
+                    connect_clicked(_),
+                    Msg::OpenUrl(target.clone())
+                );
+            }
+            grid.attach(&link, 1, i as i32, 1, 1);
+
+            let detail = Label::new(report.status.detail().unwrap_or_default().as_str());
+            detail.set_halign(Align::Start);
+            detail.set_selectable(true);
+            grid.attach(&detail, 2, i as i32, 1, 1);
+        }
+        let scroll = ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT);
+        scroll.add(&grid);
+        container.add(&scroll);
+        container.set_child_packing(&scroll, true, true, 0, PackType::Start);
+
+        dialog.get_content_area().add(&container);
+        dialog.show_all();
+    }
+
+    /// Asks how much of a site to save, returning `None` if the user cancelled.
+    fn ask_mirror_options(&self) -> Option<MirrorOptions> {
+        let defaults = {
+            let config = self.model.config.lock().unwrap();
+            MirrorOptions {
+                timeout: config.timeout(),
+                default_port: config.default_port,
+                ..MirrorOptions::default()
+            }
+        };
+
+        let dialog = Dialog::new();
+        dialog.set_title("Save site for offline reading");
+        dialog.set_transient_for(Some(&self.window));
+        dialog.set_modal(true);
+        dialog.add_button("Cancel", ResponseType::Cancel.into());
+        dialog.add_button("Save", ResponseType::Accept.into());
+
+        let grid = Grid::new();
+        grid.set_row_spacing(5);
+        grid.set_column_spacing(10);
+        grid.set_border_width(10);
+        let depth = SpinButton::new_with_range(0.0, 20.0, 1.0);
+        depth.set_value(defaults.depth as f64);
+        let max_mib = SpinButton::new_with_range(1.0, 10240.0, 1.0);
+        max_mib.set_value((defaults.max_bytes / 1024 / 1024) as f64);
+        let delay = SpinButton::new_with_range(0.0, 60.0, 0.5);
+        delay.set_digits(1);
+        delay.set_value(defaults.delay.as_millis() as f64 / 1000.0);
+
+        let rows = [
+            ("Link depth", &depth),
+            ("Size limit (MiB)", &max_mib),
+            ("Delay between requests (s)", &delay),
+        ];
+        for (i, (text, spin)) in rows.iter().enumerate() {
+            let label = Label::new(*text);
+            label.set_halign(Align::Start);
+            grid.attach(&label, 0, i as i32, 1, 1);
+            grid.attach(*spin, 1, i as i32, 1, 1);
+        }
+        dialog.get_content_area().add(&grid);
+        dialog.show_all();
+
+        let response = dialog.run();
+        let options = if response == ResponseType::Accept.into() {
+            Some(MirrorOptions {
+                depth: depth.get_value_as_int() as usize,
+                max_bytes: max_mib.get_value_as_int() as u64 * 1024 * 1024,
+                delay: Duration::from_millis((delay.get_value() * 1000.0) as u64),
+                ..defaults
+            })
+        } else {
+            None
+        };
+        dialog.destroy();
+        options
+    }
+}
+
+impl Page {
+    /// Highlights every match of the query in the find bar and goes to the first one.
+    fn find(&mut self) {
+        let buffer = match self.text_view.as_ref().and_then(|view| view.get_buffer()) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let (start, end) = buffer.get_bounds();
+        let found_tag = highlight_tag(&buffer, "found", "#fce94f");
+        let current_tag = highlight_tag(&buffer, "found-current", "#fcaf3e");
+        buffer.remove_tag(&found_tag, &start, &end);
+        buffer.remove_tag(&current_tag, &start, &end);
+
+        // icons count as one character in slices, as they do in offsets
+        let text = buffer
+            .get_slice(&start, &end, true)
+            .map(|text| text.as_str().to_owned())
+            .unwrap_or_default();
+        self.find.matches = match find::find(&text, &self.find.query()) {
+            Ok(matches) => matches,
+            Err(err) => {
+                debug!("Bad pattern: {}", err);
+                self.find.matches.clear();
+                self.find.status.set_text("Invalid regular expression");
+                return;
+            }
+        };
+        for &(start, end) in &self.find.matches {
+            let start = buffer.get_iter_at_offset(start as i32);
+            let end = buffer.get_iter_at_offset(end as i32);
+            buffer.apply_tag(&found_tag, &start, &end);
+        }
+        self.find.current = 0;
+        self.show_match();
+    }
+
+    /// Highlights the link `delta` links away from the highlighted one, skipping links that are
+    /// filtered out. Without a highlighted link, it starts from the first or the last one.
+    fn move_cursor(&mut self, delta: i32) {
+        let filter = self.filter.filter();
+        let links = self
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| {
+                row.url.is_some() && filter.matches(row.item_type, &row.text, row.url.as_ref())
+            })
+            .map(|(n, _)| n)
+            .collect::<Vec<_>>();
+        if links.is_empty() {
+            return;
+        }
+
+        let last = links.len() as i32 - 1;
+        let next = match self
+            .cursor
+            .and_then(|row| links.iter().position(|&n| n == row))
+        {
+            Some(current) => (current as i32 + delta).max(0).min(last),
+            None if delta < 0 => last,
+            None => 0,
+        };
+        self.set_cursor(Some(links[next as usize]));
+    }
+
+    /// Moves the highlight to a row, scrolling to it if it's out of sight.
+    fn set_cursor(&mut self, row: Option<usize>) {
+        self.cursor = row;
+        let view = match &self.text_view {
+            Some(view) => view,
+            None => return,
+        };
+        let buffer = match view.get_buffer() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let cursor_tag = highlight_tag(&buffer, "cursor", "#c6dcf5");
+        let (start, end) = buffer.get_bounds();
+        buffer.remove_tag(&cursor_tag, &start, &end);
+
+        if let Some(row) = row {
+            let start = buffer.get_iter_at_line(row as i32);
+            let mut end = start.clone();
+            end.forward_to_line_end();
+            buffer.apply_tag(&cursor_tag, &start, &end);
+            if !self.is_in_sight(view, &start) {
+                self.scroll_to(view, &start);
+            }
+        }
+    }
+
+    /// The link typed as a number, or else the highlighted one. The typed number is used up.
+    fn chosen_link(&mut self) -> Option<Url> {
+        let number = std::mem::replace(&mut self.number, String::new());
+        if number.is_empty() {
+            return self.rows.get(self.cursor?)?.url.clone();
+        }
+        let n = number.parse::<usize>().ok()?.checked_sub(1)?;
+        self.rows.iter().filter_map(|row| row.url.clone()).nth(n)
+    }
+
+    /// Hides the lines of a menu that don't match the filter.
+    fn filter(&self) {
+        let buffer = match self.text_view.as_ref().and_then(|view| view.get_buffer()) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let hidden_tag = hidden_tag(&buffer);
+        let (start, end) = buffer.get_bounds();
+        buffer.remove_tag(&hidden_tag, &start, &end);
+
+        let filter = self.filter.filter();
+        let links = self.rows.iter().filter(|row| row.url.is_some()).count();
+        let mut shown = 0;
+        for (n, row) in self.rows.iter().enumerate() {
+            if filter.matches(row.item_type, &row.text, row.url.as_ref()) {
+                shown += 1;
+                continue;
+            }
+            let start = buffer.get_iter_at_line(n as i32);
+            let mut end = start.clone();
+            end.forward_line();
+            buffer.apply_tag(&hidden_tag, &start, &end);
+        }
+
+        let status = if filter.is_empty() {
+            String::new()
+        } else {
+            format!("{} of {} links", shown, links)
+        };
+        self.filter.status.set_text(&status);
+    }
+
+    fn step(&mut self, backwards: bool) {
+        let count = self.find.matches.len();
+        if count == 0 {
+            return;
+        }
+        self.find.current = if backwards {
+            (self.find.current + count - 1) % count
+        } else {
+            (self.find.current + 1) % count
+        };
+        self.show_match();
+    }
+
+    /// Marks the current match and scrolls to it.
+    fn show_match(&self) {
+        let view = match &self.text_view {
+            Some(view) => view,
+            None => return,
+        };
+        let buffer = match view.get_buffer() {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        let current_tag = highlight_tag(&buffer, "found-current", "#fcaf3e");
+        let (start, end) = buffer.get_bounds();
+        buffer.remove_tag(&current_tag, &start, &end);
+
+        let status = match self.find.matches.get(self.find.current) {
+            Some(&(start, end)) => {
+                let start = buffer.get_iter_at_offset(start as i32);
+                let end = buffer.get_iter_at_offset(end as i32);
+                buffer.apply_tag(&current_tag, &start, &end);
+                self.scroll_to(view, &start);
+                format!("{} of {}", self.find.current + 1, self.find.matches.len())
+            }
+            None if self.find.query().text.is_empty() => String::new(),
+            None => "No matches".to_owned(),
+        };
+        self.find.status.set_text(&status);
+    }
+
+    fn clear_found(&mut self) {
+        self.find.matches.clear();
+        self.find.status.set_text("");
+        if let Some(buffer) = self.text_view.as_ref().and_then(|view| view.get_buffer()) {
+            let (start, end) = buffer.get_bounds();
+            buffer.remove_tag(&highlight_tag(&buffer, "found", "#fce94f"), &start, &end);
+            buffer.remove_tag(
+                &highlight_tag(&buffer, "found-current", "#fcaf3e"),
+                &start,
+                &end,
+            );
+        }
+    }
+
+    /// Scrolls the content so that `iter` is in the upper part of it.
+    fn scroll_to(&self, view: &TextView, iter: &TextIter) {
+        let y = match self.position_of(view, iter) {
+            Some(y) => y,
+            None => return,
+        };
+        if let Some(adjustment) = self.content.get_vadjustment() {
+            let page_size = adjustment.get_page_size();
+            let top = (y - page_size / 3.0).min(adjustment.get_upper() - page_size);
+            adjustment.set_value(top.max(adjustment.get_lower()));
+        }
+    }
+
+    /// Whether the line of `iter` is all in the visible part of the content.
+    fn is_in_sight(&self, view: &TextView, iter: &TextIter) -> bool {
+        let (y, adjustment) = match (self.position_of(view, iter), self.content.get_vadjustment()) {
+            (Some(y), Some(adjustment)) => (y, adjustment),
+            _ => return false,
+        };
+        let height = f64::from(view.get_iter_location(iter).height);
+        let top = adjustment.get_value();
+        y >= top && y + height <= top + adjustment.get_page_size()
+    }
+
+    /// How far down the rendered page `iter` is, in pixels.
+    fn position_of(&self, view: &TextView, iter: &TextIter) -> Option<f64> {
+        let location = view.get_iter_location(iter);
+        let (x, y) = view.buffer_to_window_coords(TextWindowType::Widget, location.x, location.y);
+        let (_, y) = view.translate_coordinates(&self.body, x, y)?;
+        Some(f64::from(y))
+    }
+}
+
+impl FilterBar {
+    fn new(relm: &Relm<Window>) -> Self {
+        let entry = SearchEntry::new();
+        entry.set_placeholder_text(Some("Filter menu"));
+        entry.set_tooltip_text(Some(
+            "Words in the names or selectors of links; \"type:dir\" or \"type:1\" for types",
+        ));
+        connect!(relm, entry, connect_search_changed(_), Msg::FilterChanged);
+        let only_dirs = CheckButton::new_with_label("Only directories");
+        connect!(relm, only_dirs, connect_toggled(_), Msg::FilterChanged);
+        let only_downloads = CheckButton::new_with_label("Only downloads");
+        connect!(relm, only_downloads, connect_toggled(_), Msg::FilterChanged);
+        let status = Label::new("");
+
+        let row = GtkBox::new(Orientation::Horizontal, 5);
+        row.set_border_width(2);
+        row.set_no_show_all(true);
+        row.add(&entry);
+        row.add(&only_dirs);
+        row.add(&only_downloads);
+        row.add(&status);
+        for child in row.get_children() {
+            child.show();
+        }
+
+        FilterBar {
+            row,
+            entry,
+            only_dirs,
+            only_downloads,
+            status,
+        }
+    }
+
+    fn filter(&self) -> Filter {
+        Filter {
+            text: self
+                .entry
+                .get_text()
+                .map(|text| text.as_str().to_owned())
+                .unwrap_or_default(),
+            only_dirs: self.only_dirs.get_active(),
+            only_downloads: self.only_downloads.get_active(),
+        }
+    }
+}
+
+impl FindBar {
+    fn new(relm: &Relm<Window>) -> Self {
+        let entry = SearchEntry::new();
+        entry.set_placeholder_text(Some("Find in page"));
+        connect!(relm, entry, connect_search_changed(_), Msg::FindChanged);
+        connect!(relm, entry, connect_activate(_), Msg::FindNext(false));
+        connect!(relm, entry, connect_next_match(_), Msg::FindNext(false));
+        connect!(relm, entry, connect_previous_match(_), Msg::FindNext(true));
+        let stream = relm.stream().clone();
+        entry.connect_key_press_event(move |_, event| {
+            let enter = event.get_keyval() == key::Return || event.get_keyval() == key::KP_Enter;
+            if enter && event.get_state().contains(ModifierType::SHIFT_MASK) {
+                stream.emit(Msg::FindNext(true));
+                Inhibit(true)
+            } else {
+                Inhibit(false)
+            }
+        });
+
+        let previous_button = Button::new_from_icon_name("go-up", IconSize::Button);
+        previous_button.set_tooltip_text(Some("Previous match (Shift+Enter)"));
+        connect!(
+            relm,
+            previous_button,
+            connect_clicked(_),
+            Msg::FindNext(true)
+        );
+        let next_button = Button::new_from_icon_name("go-down", IconSize::Button);
+        next_button.set_tooltip_text(Some("Next match (Enter)"));
+        connect!(relm, next_button, connect_clicked(_), Msg::FindNext(false));
+
+        let match_case = CheckButton::new_with_label("Match case");
+        connect!(relm, match_case, connect_toggled(_), Msg::FindChanged);
+        let regex = CheckButton::new_with_label("Regular expression");
+        connect!(relm, regex, connect_toggled(_), Msg::FindChanged);
+        let status = Label::new("");
+
+        let row = GtkBox::new(Orientation::Horizontal, 5);
+        row.add(&entry);
+        row.add(&previous_button);
+        row.add(&next_button);
+        row.add(&match_case);
+        row.add(&regex);
+        row.add(&status);
+
+        let bar = SearchBar::new();
+        bar.add(&row);
+        bar.connect_entry(&entry);
+        bar.set_show_close_button(true);
+        // Escape and the close button both end the search
+        let stream = relm.stream().clone();
+        bar.connect_property_search_mode_enabled_notify(move |bar| {
+            if !bar.get_search_mode() {
+                stream.emit(Msg::FindClosed);
+            }
+        });
+
+        FindBar {
+            bar,
+            entry,
+            match_case,
+            regex,
+            status,
+            matches: Vec::new(),
+            current: 0,
+        }
+    }
+
+    fn query(&self) -> Query {
+        Query {
+            text: self
+                .entry
+                .get_text()
+                .map(|text| text.as_str().to_owned())
+                .unwrap_or_default(),
+            match_case: self.match_case.get_active(),
+            regex: self.regex.get_active(),
+        }
+    }
+}
+
+/// A table of the entries parsed from a menu, one row per line of the menu.
+fn entry_list(entries: &[MenuEntry]) -> ScrolledWindow {
+    let store = ListStore::new(&[String::static_type(); 5]);
+    for line in entries.iter().flat_map(MenuEntry::lines) {
+        let values = [
+            line.item_type.to_string(),
+            line.display,
+            line.selector.unwrap_or_default(),
+            line.host.unwrap_or_default(),
+            line.port.map(|port| port.to_string()).unwrap_or_default(),
+        ];
+        let values: Vec<&dyn ToValue> = values.iter().map(|value| value as &dyn ToValue).collect();
+        store.insert_with_values(None, &[0, 1, 2, 3, 4], &values);
+    }
+
+    let tree_view = TreeView::new_with_model(&store);
+    add_text_columns(&tree_view, &["Type", "Display", "Selector", "Host", "Port"]);
+    let scroll = ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT);
+    scroll.add(&tree_view);
+    scroll
+}
+
+/// Adds the lines of a response's source to the end of a buffer, with tabs and line endings
+/// grayed out and the lines a menu left out highlighted.
+fn insert_source(buffer: &TextBuffer, lines: &[SourceLine]) {
+    let control_tag = source_tag(buffer, "control");
+    let invalid_tag = source_tag(buffer, "invalid");
+    let rejected_tag = source_tag(buffer, "rejected");
+    let mut iter = buffer.get_end_iter();
+    for line in lines {
+        let start = iter.get_offset();
+        for (kind, text) in &line.spans {
+            match kind {
+                source::Kind::Text => buffer.insert(&mut iter, text),
+                source::Kind::Control => buffer.insert_with_tags(&mut iter, text, &[&control_tag]),
+                source::Kind::Invalid => buffer.insert_with_tags(&mut iter, text, &[&invalid_tag]),
+            }
+        }
+        // the line ending is only a symbol, the buffer still needs a real one
+        buffer.insert(&mut iter, "\n");
+        if line.rejected {
+            let start = buffer.get_iter_at_offset(start);
+            buffer.apply_tag(&rejected_tag, &start, &iter);
+        }
+    }
+}
+
+/// A tag for showing source, added to the buffer the first time it's needed.
+fn source_tag(buffer: &TextBuffer, name: &str) -> TextTag {
+    let table = buffer.get_tag_table();
+    if let Some(tag) = table.as_ref().and_then(|table| table.lookup(name)) {
+        return tag;
+    }
+    let tag = TextTag::new(name);
+    match name {
+        "control" => tag.set_property_foreground(Some("gray")),
+        "invalid" => {
+            tag.set_property_background(Some("#f4a6a6"));
+            tag.set_property_foreground(Some("black"));
+        }
+        "rejected" => tag.set_property_paragraph_background(Some("#fbdcdc")),
+        // bold
+        "heading" => tag.set_property_weight(700),
+        _ => (),
+    }
+    if let Some(table) = table {
+        table.add(&tag);
+    }
+    tag
+}
+
+/// Adds a column to a tree view for every title, showing the text in the model's columns in
+/// the same order.
+fn add_text_columns(tree_view: &TreeView, titles: &[&str]) {
+    for (i, title) in titles.iter().enumerate() {
+        let column = TreeViewColumn::new();
+        column.set_title(title);
+        column.set_resizable(true);
+        let cell = CellRendererText::new();
+        column.pack_start(&cell, true);
+        column.add_attribute(&cell, "text", i as i32);
+        tree_view.append_column(&column);
+    }
+}
+
+/// A tag highlighting part of a page, added to the buffer the first time it's needed.
+fn highlight_tag(buffer: &TextBuffer, name: &str, color: &str) -> TextTag {
+    let table = buffer.get_tag_table();
+    if let Some(tag) = table.as_ref().and_then(|table| table.lookup(name)) {
+        return tag;
+    }
+    let tag = TextTag::new(name);
+    tag.set_property_background(Some(color));
+    tag.set_property_foreground(Some("black"));
+    if let Some(table) = table {
+        table.add(&tag);
+    }
+    tag
+}
+
+/// A tag hiding the lines of a menu that are filtered out.
+fn hidden_tag(buffer: &TextBuffer) -> TextTag {
+    let table = buffer.get_tag_table();
+    if let Some(tag) = table.as_ref().and_then(|table| table.lookup("filtered")) {
+        return tag;
+    }
+    let tag = TextTag::new("filtered");
+    tag.set_property_invisible(true);
+    if let Some(table) = table {
+        table.add(&tag);
+    }
+    tag
+}
+
+/// What a key from the `[keys]` config table does.
+#[derive(Clone, Copy)]
+enum KeyAction {
+    NextLink,
+    PreviousLink,
+    FollowLink,
+    Up,
+    Find,
+}
+
+impl KeyAction {
+    fn is_for_menus(self) -> bool {
+        match self {
+            KeyAction::NextLink | KeyAction::PreviousLink | KeyAction::FollowLink => true,
+            KeyAction::Up | KeyAction::Find => false,
+        }
+    }
+
+    fn msg(self) -> Msg {
+        match self {
+            KeyAction::NextLink => Msg::MoveCursor(1),
+            KeyAction::PreviousLink => Msg::MoveCursor(-1),
+            KeyAction::FollowLink => Msg::FollowLink,
+            KeyAction::Up => Msg::GoUp,
+            KeyAction::Find => Msg::Find,
+        }
+    }
+}
+
+/// The keys from the config with what they do. Keys GTK doesn't know are skipped.
+fn key_bindings(keys: &Keys) -> Vec<(u32, ModifierType, KeyAction)> {
+    let actions = [
+        (&keys.next_link, KeyAction::NextLink),
+        (&keys.previous_link, KeyAction::PreviousLink),
+        (&keys.follow_link, KeyAction::FollowLink),
+        (&keys.up, KeyAction::Up),
+        (&keys.find, KeyAction::Find),
+    ];
+    let mut bindings = Vec::new();
+    for (names, action) in actions.iter() {
+        for name in names.iter() {
+            let (key, modifiers) = gtk::accelerator_parse(name);
+            if key == 0 {
+                warn!("Unknown key {:?} in the [keys] config", name);
+                continue;
+            }
+            bindings.push((gdk::keyval_to_lower(key), modifiers, *action));
+        }
+    }
+    bindings
+}
+
+/// Whether what a link points to can be saved, rather than being a session or a search.
+fn can_save(item_type: ItemType, url: &Url) -> bool {
+    let fetchable = match item_type {
+        ItemType::CsoServer
+        | ItemType::Error
+        | ItemType::IndexServer
+        | ItemType::Telnet
+        | ItemType::Tn3270 => false,
+        _ => true,
+    };
+    fetchable && (url.scheme() == "gopher" || url.scheme() == "file")
+}
+
+/// The path chosen in a dialog asking where to save a file, if any.
+fn chosen_path(dialog: &FileChooserDialog) -> Option<PathBuf> {
+    if dialog.run() == ResponseType::Accept.into() {
+        dialog.get_filename()
+    } else {
+        None
+    }
+}
+
+/// The format picked to save a page in, or `None` to save it as it was received.
+fn chosen_format(formats: &ComboBoxText) -> Option<Format> {
+    // "raw" isn't a format, so it doesn't parse
+    formats.get_active_id()?.parse().ok()
+}
+
+/// A name to suggest when saving what `url` points to.
+fn file_name(url: &Url) -> String {
+    let name = match url.scheme() {
+        "gopher" => Request::from_url(url.clone())
+            .ok()
+            .and_then(|request| request.file_name()),
+        "file" => url
+            .to_file_path()
+            .ok()
+            .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned())),
+        _ => None,
+    };
+    name.unwrap_or_else(|| "index".to_owned())
+}
+
+/// What the status bar says about a link: its type, where it goes and the server it's on.
+fn describe_link(item_type: ItemType, url: &Url) -> String {
+    let mut parts = vec![item_type.name(), url.to_string()];
+    if let Some(host) = url.host_str() {
+        parts.push(match url.port_or_known_default() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_owned(),
+        });
+    }
+    parts.join("  |  ")
+}
+
+fn format_seconds(elapsed: Duration) -> String {
+    format!("{:.1} s", elapsed.as_millis() as f64 / 1000.0)
+}
+
+/// Shows why the address in the address bar can't be opened, or clears that when `err` is `None`.
+fn mark_invalid(entry: &Entry, err: Option<&str>) {
+    let style = entry.get_style_context();
+    match err {
+        Some(err) => {
+            style.add_class("error");
+            entry.set_icon_from_icon_name(EntryIconPosition::Secondary, Some("dialog-error"));
+            entry.set_icon_tooltip_text(EntryIconPosition::Secondary, Some(err));
+        }
+        None => {
+            style.remove_class("error");
+            entry.set_icon_from_icon_name(EntryIconPosition::Secondary, None);
+        }
+    }
+}
+
+/// The page one level up: the menu above a gopher selector, or the directory above a file.
+fn up_url(url: &Url) -> Option<Url> {
+    match url.scheme() {
+        "gopher" => gopher::parent_url(url),
+        "file" => {
+            let parent = if url.path().ends_with('/') { ".." } else { "." };
+            url.join(parent).ok().filter(|parent| parent != url)
+        }
+        _ => None,
+    }
+}
+
+/// The lines of a menu, or nothing for other responses.
+fn rows_of(response: &Response) -> Vec<MenuRow> {
+    match &response.kind {
+        ResponseKind::Menu(entries) => menu_rows(entries),
+        _ => Vec::new(),
+    }
+}
+
+/// The text view that shows a page.
+fn text_view_in(widget: &gtk::Widget) -> Option<TextView> {
+    if let Ok(view) = widget.clone().downcast::<TextView>() {
+        return Some(view);
+    }
+    let container = widget.clone().downcast::<gtk::Container>().ok()?;
+    container.get_children().iter().find_map(text_view_in)
+}
+
+impl MirrorView {
+    fn new(relm: &Relm<Window>, root: &Path) -> Self {
+        let window = gtk::Window::new(WindowType::Toplevel);
+        window.set_title("Saving site");
+        window.set_default_size(400, -1);
+
+        let container = GtkBox::new(Orientation::Vertical, 5);
+        container.set_border_width(10);
+        let label = Label::new("Starting...");
+        label.set_halign(Align::Start);
+        container.add(&label);
+        let progress_bar = ProgressBar::new();
+        progress_bar.set_show_text(true);
+        container.add(&progress_bar);
+
+        let open_button = Button::new_with_label("Open");
+        open_button.set_sensitive(false);
+        if let Ok(url) = Url::from_directory_path(root) {
+            connect!(
+                relm,
+                open_button,
+                connect_clicked(_),
+                Msg::OpenUrl(url.clone())
+            );
+        }
+        container.add(&open_button);
+        window.add(&container);
+
+        MirrorView {
+            window,
+            label,
+            progress_bar,
+            open_button,
+        }
+    }
+
+    fn update(&self, progress: &MirrorProgress) {
+        let status = match &progress.current {
+            _ if progress.finished => format!(
+                "Done: {} items saved to {}",
+                progress.saved,
+                progress.root.display()
+            ),
+            Some(url) => format!("Saving {}", url),
+            None => "Starting...".to_owned(),
+        };
+        self.label.set_text(&status);
+
+        let fraction = progress.bytes as f64 / progress.max_bytes as f64;
+        self.progress_bar.set_fraction(fraction.min(1.0));
+        self.progress_bar.set_text(Some(
+            format!(
+                "{} saved, {} failed, {} queued, {}",
+                progress.saved,
+                progress.failed,
+                progress.queued,
+                about::format_size(progress.bytes as usize)
+            )
+            .as_str(),
+        ));
+        self.open_button.set_sensitive(progress.finished);
+    }
+}
+
+impl Inspector {
+    fn new(relm: &Relm<Window>) -> Self {
+        let panel = GtkBox::new(Orientation::Vertical, 5);
+        panel.set_border_width(5);
+        panel.set_size_request(-1, 200);
+        let toolbar = GtkBox::new(Orientation::Horizontal, 5);
+        let count = Label::new("No requests yet");
+        count.set_halign(Align::Start);
+        toolbar.pack_start(&count, true, true, 0);
+        let clear_button = Button::new_with_label("Clear");
+        connect!(relm, clear_button, connect_clicked(_), Msg::ClearExchanges);
+        toolbar.pack_end(&clear_button, false, false, 0);
+        panel.pack_start(&toolbar, false, false, 0);
+
+        // after the columns that are shown is the index of the exchange
+        let mut types = vec![String::static_type(); inspector::COLUMNS.len()];
+        types.push(u32::static_type());
+        let store = ListStore::new(&types);
+        let tree_view = TreeView::new_with_model(&store);
+        add_text_columns(&tree_view, &inspector::COLUMNS);
+        let stream = relm.stream().clone();
+        tree_view.get_selection().connect_changed(move |selection| {
+            if let Some((model, iter)) = selection.get_selected() {
+                let index = model.get_value(&iter, inspector::COLUMNS.len() as i32);
+                if let Some(index) = index.get::<u32>() {
+                    stream.emit(Msg::InspectExchange(index as usize));
+                }
+            }
+        });
+        let list_scroll = ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT);
+        list_scroll.add(&tree_view);
+
+        let detail = TextView::new();
+        detail.set_editable(false);
+        detail.set_property_monospace(true);
+        let detail_scroll = ScrolledWindow::new(NONE_ADJUSTMENT, NONE_ADJUSTMENT);
+        detail_scroll.add(&detail);
+
+        let paned = Paned::new(Orientation::Horizontal);
+        paned.pack1(&list_scroll, true, false);
+        paned.pack2(&detail_scroll, true, false);
+        paned.set_position(550);
+        panel.pack_start(&paned, true, true, 0);
+
+        Inspector {
+            panel,
+            store,
+            count,
+            detail,
+            exchanges: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, exchange: Exchange) {
+        let columns = exchange.columns();
+        let index = self.exchanges.len() as u32;
+        let mut values: Vec<&dyn ToValue> =
+            columns.iter().map(|value| value as &dyn ToValue).collect();
+        values.push(&index);
+        let indices = (0..values.len() as u32).collect::<Vec<_>>();
+        self.store.insert_with_values(None, &indices, &values);
+        self.exchanges.push(exchange);
+        self.update_count();
+    }
+
+    fn clear(&mut self) {
+        self.store.clear();
+        self.exchanges.clear();
+        if let Some(buffer) = self.detail.get_buffer() {
+            buffer.set_text("");
+        }
+        self.update_count();
+    }
+
+    fn update_count(&self) {
+        let failed = self
+            .exchanges
+            .iter()
+            .filter(|exchange| exchange.response.is_err())
+            .count();
+        let received = self
+            .exchanges
+            .iter()
+            .map(|exchange| exchange.metrics.received)
+            .sum();
+        self.count.set_text(&format!(
+            "{} requests, {} failed, {} received",
+            self.exchanges.len(),
+            failed,
+            about::format_size(received)
+        ));
+    }
+
+    /// Shows the raw request and response of an exchange next to the list.
+    fn show(&self, index: usize) {
+        let (exchange, buffer) = match (self.exchanges.get(index), self.detail.get_buffer()) {
+            (Some(exchange), Some(buffer)) => (exchange, buffer),
+            _ => return,
+        };
+        let metrics = &exchange.metrics;
+        buffer.set_text("");
+        let heading_tag = source_tag(&buffer, "heading");
+        let heading = |text: &str| {
+            let mut iter = buffer.get_end_iter();
+            buffer.insert_with_tags(&mut iter, text, &[&heading_tag]);
+        };
+
+        let to = match metrics.address {
+            Some(address) => address.to_string(),
+            None => metrics.url.host_str().unwrap_or("").to_owned(),
+        };
+        if exchange.cached {
+            heading("Request answered from the cache\n");
+        } else {
+            heading(&format!("Request to {}\n", to));
+        }
+        insert_source(&buffer, &source::lines(&metrics.request, false));
+        match &exchange.response {
+            Ok(body) => {
+                let mut size = about::format_size(metrics.received);
+                if body.len() < metrics.received {
+                    size += &format!(", first {} kept", about::format_size(body.len()));
+                }
+                heading(&format!("\nResponse, {}\n", size));
+                let is_menu = match metrics.item_type {
+                    ItemType::Dir | ItemType::IndexServer => true,
+                    _ => false,
+                };
+                insert_source(&buffer, &source::lines(body, is_menu));
+            }
+            Err(err) => heading(&format!("\nNo response: {}\n", err)),
+        }
+    }
+}
+
+/// Replaces the style of page contents with the font and colors from `config`.
+fn load_css(provider: &CssProvider, config: &Config) {
+    if let Err(err) = provider.load_from_data(config.css().as_bytes()) {
+        error!("Could not load style: {}", err);
+    }
+}
+
 impl Widget for Window {
     type Root = gtk::Window;
 
@@ -133,14 +2440,154 @@ impl Widget for Window {
     }
     fn view(relm: &Relm<Self>, model: Self::Model) -> Self {
         let window = gtk::Window::new(WindowType::Toplevel);
-        window.set_title("gopher-browser");
-        window.set_default_size(854, 480);
+        let title = if model.private {
+            "gopher-browser (private)"
+        } else {
+            "gopher-browser"
+        };
+        window.set_title(title);
+        {
+            let config = model.config.lock().unwrap();
+            window.set_default_size(config.window.width, config.window.height);
+        }
+        let css_provider = CssProvider::new();
+        load_css(&css_provider, &model.config.lock().unwrap());
+        if let Some(screen) = window.get_screen() {
+            StyleContext::add_provider_for_screen(
+                &screen,
+                &css_provider,
+                gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
+            );
+        }
+
+        let header = HeaderBar::new();
+        header.set_title(Some(title));
+        header.set_show_close_button(true);
+
+        let preferences_button = Button::new_from_icon_name("preferences-system", IconSize::Button);
+        preferences_button.set_tooltip_text(Some("Preferences"));
+        connect!(
+            relm,
+            preferences_button,
+            connect_clicked(_),
+            Msg::ShowPreferences
+        );
+        header.pack_end(&preferences_button);
+
+        let offline_button = ToggleButton::new();
+        offline_button.set_image(Some(&Image::new_from_icon_name(
+            "network-offline",
+            IconSize::Button,
+        )));
+        offline_button.set_tooltip_text(Some("Offline mode: only show previously visited pages"));
+        connect!(
+            relm,
+            offline_button,
+            connect_toggled(button),
+            Msg::SetOffline(button.get_active())
+        );
+        header.pack_end(&offline_button);
+
+        let inspector_button = ToggleButton::new();
+        inspector_button.set_image(Some(&Image::new_from_icon_name(
+            "network-transmit-receive",
+            IconSize::Button,
+        )));
+        inspector_button.set_tooltip_text(Some("Network inspector (Ctrl+Shift+I)"));
+        connect!(
+            relm,
+            inspector_button,
+            connect_toggled(button),
+            Msg::ShowInspector(button.get_active())
+        );
+        header.pack_end(&inspector_button);
+
+        let bookmarks_button = MenuButton::new();
+        bookmarks_button.set_image(Some(&Image::new_from_icon_name(
+            "user-bookmarks",
+            IconSize::Button,
+        )));
+        bookmarks_button.set_tooltip_text(Some("Bookmarks"));
+        let bookmarks_menu = Menu::new();
+        bookmarks_button.set_popup(Some(&bookmarks_menu));
+        header.pack_end(&bookmarks_button);
+
+        let history_button = MenuButton::new();
+        history_button.set_image(Some(&Image::new_from_icon_name(
+            "document-open-recent",
+            IconSize::Button,
+        )));
+        history_button.set_tooltip_text(Some("History"));
+        let history_popover = Popover::new(Some(&history_button));
+        let history_box = GtkBox::new(Orientation::Vertical, 5);
+        history_box.set_border_width(5);
+        let history_search = SearchEntry::new();
+        history_search.set_placeholder_text(Some("Search history"));
+        let stream = relm.stream().clone();
+        let popover = history_popover.clone();
+        history_search.connect_activate(move |entry| {
+            let query = entry
+                .get_text()
+                .map(|text| text.as_str().to_owned())
+                .unwrap_or_default();
+            let mut url = Url::parse("about:history").unwrap();
+            if !query.is_empty() {
+                url.query_pairs_mut().append_pair("q", &query);
+            }
+            popover.hide();
+            stream.emit(Msg::OpenUrl(url));
+        });
+        history_box.add(&history_search);
+        let all_history = Button::new_with_label("Show all history");
+        let history_url = Url::parse("about:history").unwrap();
+        connect!(
+            relm,
+            all_history,
+            connect_clicked(_),
+            Msg::OpenUrl(history_url.clone())
+        );
+        history_box.add(&all_history);
+        history_box.show_all();
+        history_popover.add(&history_box);
+        history_button.set_popover(Some(&history_popover));
+        header.pack_end(&history_button);
+        window.set_titlebar(Some(&header));
+
+        let accel_group = AccelGroup::new();
+        let (key, modifier) = gtk::accelerator_parse("<Primary>d");
+        let stream = relm.stream().clone();
+        accel_group.connect_accel_group(key, modifier, AccelFlags::VISIBLE, move |_, _, _, _| {
+            stream.emit(Msg::BookmarkPage);
+            true
+        });
+        let (key, modifier) = gtk::accelerator_parse("<Primary>f");
+        let stream = relm.stream().clone();
+        accel_group.connect_accel_group(key, modifier, AccelFlags::VISIBLE, move |_, _, _, _| {
+            stream.emit(Msg::Find);
+            true
+        });
+        let (key, modifier) = gtk::accelerator_parse("<Primary><Shift>i");
+        accel_group.connect_accel_group(key, modifier, AccelFlags::VISIBLE, move |_, _, _, _| {
+            inspector_button.set_active(!inspector_button.get_active());
+            true
+        });
+        window.add_accel_group(&accel_group);
 
         let notebook = gtk::Notebook::new();
         notebook.set_show_tabs(true);
-        window.add(&notebook);
+        let statusbar = Statusbar::new();
+        let inspector = Inspector::new(relm);
+        let paned = Paned::new(Orientation::Vertical);
+        paned.pack1(&notebook, true, false);
+        paned.pack2(&inspector.panel, false, false);
+        let container = GtkBox::new(Orientation::Vertical, 0);
+        container.add(&paned);
+        container.set_child_packing(&paned, true, true, 0, PackType::Start);
+        container.add(&statusbar);
+        window.add(&container);
 
         window.show_all();
+        inspector.panel.hide();
         connect!(
             relm,
             window,
@@ -148,10 +2595,27 @@ impl Widget for Window {
             return (Some(Msg::Quit), Inhibit(false))
         );
 
-        Window {
+        let history_store = ListStore::new(&[String::static_type()]);
+        for url in model.history.urls() {
+            history_store.insert_with_values(None, &[0], &[&url.to_string()]);
+        }
+
+        let window = Window {
             window,
             notebook,
+            bookmarks_menu,
+            history_store,
+            pages: Vec::new(),
+            mirror_views: HashMap::new(),
+            inspector,
+            statusbar,
+            loading: HashMap::new(),
+            targets: HashMap::new(),
+            save_targets: HashMap::new(),
+            css_provider,
             model,
-        }
+        };
+        window.fill_bookmarks_menu();
+        window
     }
 }